@@ -0,0 +1,474 @@
+//! Working with a catalog from the shell instead of the window, e.g. `catalog -c home.sqlite find lamp`.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use serde_json::json;
+
+use catalog::{export, import::{self, ColumnRole}, AddOutcome, CatalogError, CatalogStore, FieldInfo, ItemInfo, LocationLevel, NewItem, SearchFilter, SearchMode};
+
+
+/// The arguments Catalog was started with
+#[derive(Debug, Parser)]
+#[command(name = "catalog", version, about = "A simple cataloging app for the home cataloger. Run without a command to open the window.")]
+pub struct Cli {
+    /// The catalog to work on, a `.sqlite` file
+    #[arg(short, long, global = true)]
+    catalog: Option<PathBuf>,
+    /// Print results as JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Add an item, or add to the quantity of an item with the same name in the same location.
+    /// With `--json` the item has `"merged": true` when its quantity was added to.
+    Add {
+        name: String,
+        /// Where the item is kept, levels separated by `/`, each optionally written as `Kind:Name`,
+        /// e.g. `Room:Garage/Rack:Rack 2/Shelf 3`
+        #[arg(short, long)]
+        location: String,
+        #[arg(short, long, default_value = "")]
+        notes: String,
+        #[arg(short, long, default_value_t = 1)]
+        quantity: i64,
+        /// A tag to give the item, can be given more than once
+        #[arg(short, long = "tag")]
+        tags: Vec<String>,
+        /// A custom field written as `Name=Value`, can be given more than once
+        #[arg(short, long = "field")]
+        fields: Vec<String>,
+        /// Add a separate item even if one with the same name is already in that location
+        #[arg(long)]
+        separate: bool,
+    },
+    /// Search for items by name
+    Find {
+        query: String,
+        /// Can't be combined with `--in`, which always finds names containing the query
+        #[arg(short, long, value_enum, default_value_t = Mode::Contains, conflicts_with = "within")]
+        mode: Mode,
+        /// Only find items with this tag, can be given more than once
+        #[arg(short, long = "tag")]
+        tags: Vec<String>,
+        /// Only find items within this location, levels separated by `/`, an empty level matching any location
+        #[arg(long = "in")]
+        within: Option<String>,
+    },
+    /// Move items to the trash, by id. Nothing is removed if any of the ids is wrong.
+    Rm {
+        #[arg(required = true)]
+        ids: Vec<i64>,
+        /// Delete the items for good instead, along with their photos, including items already in the trash
+        #[arg(long)]
+        purge: bool,
+    },
+    /// Move items to another location, by id. Nothing is moved if any of the ids is wrong.
+    Mv {
+        #[arg(required = true)]
+        ids: Vec<i64>,
+        /// Where the items are going, written the same way as for `add`
+        #[arg(short, long)]
+        to: String,
+    },
+    /// List the locations and items directly inside of a location, or the top level locations
+    Ls {
+        /// Levels separated by `/`
+        location: Option<String>,
+    },
+    /// Add every item in a CSV file, picking what each column holds by its header
+    Import {
+        file: PathBuf,
+        /// What a column holds, written as `Header=Role`, can be given more than once.
+        /// The role is one of skip, location, kind, name, notes, quantity, tags or the name of a custom field.
+        #[arg(long = "column")]
+        columns: Vec<String>,
+    },
+    /// Write every item to a CSV file, or a JSON file if it ends in `.json`
+    Export {
+        file: PathBuf,
+    },
+}
+
+/// How `find` matches item names, see `SearchMode`
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Mode {
+    Contains,
+    StartsWith,
+    Fuzzy,
+    /// Names and notes, using the full text index
+    Text,
+}
+
+impl From<Mode> for SearchMode {
+    fn from(mode: Mode) -> SearchMode {
+        match mode {
+            Mode::Contains => SearchMode::Substring,
+            Mode::StartsWith => SearchMode::Prefix,
+            Mode::Fuzzy => SearchMode::Fuzzy,
+            Mode::Text => SearchMode::FullText,
+        }
+    }
+}
+
+/// Runs the command of `cli` against its catalog, printing the result, and returns the exit code of the process
+pub fn run(cli: Cli) -> i32 {
+    let Some(command) = cli.command else {
+        return 0;
+    };
+    let Some(catalog) = cli.catalog else {
+        eprintln!("Pass the catalog to work on with --catalog");
+        return 2;
+    };
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 1;
+        }
+    };
+
+    runtime.block_on(async {
+        let store = match CatalogStore::open(&catalog).await {
+            Ok(store) => store,
+            Err(err) => {
+                eprintln!("Could not open {}: {}", catalog.display(), err);
+                return 1;
+            }
+        };
+
+        let result = execute(&store, command, cli.json).await;
+        store.close().await;
+
+        match result {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("{}", err);
+                1
+            }
+        }
+    })
+}
+
+/// Splits a location written like `Room:Garage/Rack 2` into its levels,
+/// where a level without a kind keeps the kind of the existing location, or gets "Location" if it is new
+fn parse_location(location: &str) -> Vec<LocationLevel> {
+    location.split('/')
+        .map(str::trim)
+        .filter(|level| !level.is_empty())
+        .map(|level| {
+            let (kind, name) = match level.split_once(':') {
+                Some((kind, name)) => (kind.trim(), name.trim()),
+                None => ("", level),
+            };
+            LocationLevel {
+                kind: kind.to_string(),
+                name: name.to_string(),
+                description: String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Prints `value` as JSON on a single line, keeping the order of its fields
+fn print_json<T: Serialize>(value: &T) {
+    match serde_json::to_string(value) {
+        Ok(json) => println!("{}", json),
+        Err(err) => eprintln!("{}", err),
+    }
+}
+
+fn print_items(items: &[ItemInfo], json: bool) {
+    if json {
+        let items = items.iter()
+            .map(|item| export::JsonItem::new(item, &[]))
+            .collect::<Vec<_>>();
+        print_json(&items);
+        return;
+    }
+
+    for item in items {
+        print_item(item);
+    }
+}
+
+fn print_item(item: &ItemInfo) {
+    let mut line = format!("{:>6}  {}", item.item_id, item.item_name);
+    if item.quantity > 1 {
+        line.push_str(&format!(" (x{})", item.quantity));
+    }
+    line.push_str(&format!("  in {}", item.location_path));
+    if !item.tags.is_empty() {
+        line.push_str(&format!("  [{}]", item.tags.join(", ")));
+    }
+    println!("{}", line);
+}
+
+async fn execute(store: &CatalogStore, command: Command, json: bool) -> Result<(), CatalogError> {
+    match command {
+        Command::Add { name, location, notes, quantity, tags, fields, separate } => {
+            let location = parse_location(&location);
+            if location.is_empty() {
+                return Err(CatalogError::Invalid(String::from("the item needs a location")));
+            }
+            if quantity < 1 {
+                return Err(CatalogError::Invalid(String::from("the quantity has to be at least 1")));
+            }
+
+            let known = store.fields().await?;
+            let fields = fields.iter()
+                .map(|field| field_value(&known, field))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(CatalogError::Invalid)?;
+
+            let item = NewItem { location, name, notes, quantity, tags, fields };
+            let (item, merged) = match store.add_item(&item, separate).await? {
+                AddOutcome::Added(item) => (item, false),
+                AddOutcome::IdenticalFound(identical) => {
+                    // only the quantity is added to, so refuse rather than drop the rest
+                    if !item.notes.is_empty() || !item.tags.is_empty() || !item.fields.is_empty() {
+                        return Err(CatalogError::Invalid(format!(
+                            "there is already a {} there (id {}), and --notes, --tag and --field only apply to new items: \
+                            add --separate to add another one, or leave them out to add to its quantity",
+                            identical.item_name,
+                            identical.item_id
+                        )));
+                    }
+                    (store.change_quantity(identical.item_id, quantity).await?, true)
+                }
+            };
+
+            if json {
+                /// The item along with whether it was already there
+                #[derive(Serialize)]
+                struct Added<'a> {
+                    #[serde(flatten)]
+                    item: export::JsonItem<'a>,
+                    merged: bool,
+                }
+                print_json(&Added { item: export::JsonItem::new(&item, &[]), merged });
+            } else {
+                if merged {
+                    println!("Added {} to the quantity of the identical item already there", quantity);
+                }
+                print_item(&item);
+            }
+        }
+        Command::Find { query, mode, tags, within } => {
+            let filter = SearchFilter { tags, fields: Vec::new() };
+            let items = match within {
+                Some(within) => {
+                    let location = within.split('/')
+                        .map(|level| level.trim().to_string())
+                        .collect::<Vec<_>>();
+                    store.search_by_location(&location, &query, &filter).await?
+                }
+                None => store.search(&query, mode.into(), &filter).await?,
+            };
+
+            if items.is_empty() && !json {
+                println!("Nothing found");
+            }
+            print_items(&items, json);
+        }
+        Command::Rm { ids, purge } => {
+            let ids = unique(ids);
+            let removed = if purge {
+                store.purge_items(&ids).await?
+            } else {
+                store.delete_items(&ids).await?
+            };
+
+            if json {
+                print_items(&removed, true);
+            } else {
+                let action = if purge { "Deleted" } else { "Moved to the trash:" };
+                for item in removed.iter() {
+                    println!("{} {} ({})", action, item.item_name, item.item_id);
+                }
+            }
+        }
+        Command::Mv { ids, to } => {
+            let location = parse_location(&to);
+            if location.is_empty() {
+                return Err(CatalogError::Invalid(String::from("say where the items are going")));
+            }
+
+            let ids = unique(ids);
+            for id in ids.iter().copied() {
+                let item = store.load_item(id).await?.item;
+                if item.deleted_at.is_some() {
+                    return Err(CatalogError::Invalid(format!("{} ({}) is in the trash", item.item_name, id)));
+                }
+            }
+
+            let mut moved = Vec::new();
+            for (id, _) in store.move_items(&ids, &location).await? {
+                moved.push(store.load_item(id).await?.item);
+            }
+            print_items(&moved, json);
+        }
+        Command::Ls { location } => {
+            let path = location.as_deref()
+                .map(parse_location)
+                .unwrap_or_default();
+
+            // walk down from the top level one location at a time
+            let mut listing = store.browse(None).await?;
+            for level in path.iter() {
+                let Some(child) = listing.children.iter().find(|child| child.location.name.eq_ignore_ascii_case(&level.name)) else {
+                    return Err(CatalogError::Invalid(format!("there is no location named {} there", level.name)));
+                };
+                listing = store.browse(Some(child.location.location_id)).await?;
+            }
+
+            if json {
+                let locations = listing.children.iter()
+                    .map(|child| json!({
+                        "kind": child.location.kind,
+                        "name": child.location.name,
+                        "description": child.location.description,
+                        "item_count": child.item_count,
+                    }))
+                    .collect::<Vec<_>>();
+                let items = listing.items.iter()
+                    .map(|item| export::JsonItem::new(item, &[]))
+                    .collect::<Vec<_>>();
+                let path = listing.trail.iter()
+                    .map(|location| location.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" / ");
+
+                #[derive(Serialize)]
+                struct Listing<'a> {
+                    location: String,
+                    locations: Vec<serde_json::Value>,
+                    items: Vec<export::JsonItem<'a>>,
+                }
+                print_json(&Listing { location: path, locations, items });
+            } else {
+                for child in listing.children.iter() {
+                    println!("{}: {}/  ({} item(s))", child.location.kind, child.location.name, child.item_count);
+                }
+                print_items(&listing.items, false);
+            }
+        }
+        Command::Import { file, columns } => {
+            let file_name = file.display().to_string();
+            let contents = import::read(&file).await?;
+            let fields = store.fields().await?;
+
+            let mut roles = contents.guess_roles(&fields);
+            let mut picked = HashSet::new();
+            for column in columns.iter() {
+                let (index, role) = column_role(&contents.headers, &fields, column)
+                    .map_err(CatalogError::Invalid)?;
+                roles[index] = role;
+                picked.insert(index);
+            }
+            for (index, header) in contents.headers.iter().enumerate() {
+                if roles[index] == ColumnRole::Skip && !picked.contains(&index) && !import::is_export_only(header) {
+                    eprintln!("Skipping the {} column, pick what it holds with --column \"{}=ROLE\"", header, header);
+                }
+            }
+
+            if let Some(problem) = import::check_roles(&roles) {
+                return Err(CatalogError::Invalid(format!("{}: name the columns Name and, for example, Rack, Shelf and Basket, or pick them with --column", problem)));
+            }
+
+            let mut rows = Vec::new();
+            let mut errors = Vec::new();
+            for row in import::prepare(&contents, &roles, &fields) {
+                match row {
+                    Ok(row) => rows.push(row),
+                    Err(err) => errors.push(err),
+                }
+            }
+            if !errors.is_empty() {
+                return Err(CatalogError::Invalid(format!("nothing was imported\n{}", errors.join("\n"))));
+            }
+
+            let imported = store.import(&rows, &file_name).await?;
+            if json {
+                println!("{}", json!({ "imported": imported, "file": file_name }));
+            } else {
+                println!("Imported {} item(s) from {}", imported, file_name);
+            }
+        }
+        Command::Export { file } => {
+            let export = store.export().await?;
+            export::save(&file, &export).await?;
+
+            let exported = export.items.len();
+            let file_name = file.display().to_string();
+            if json {
+                println!("{}", json!({ "exported": exported, "file": file_name }));
+            } else {
+                println!("Exported {} item(s) to {}", exported, file_name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `ids` without any repeats, in the order they were first given
+fn unique(ids: Vec<i64>) -> Vec<i64> {
+    let mut seen = HashSet::new();
+    ids.into_iter()
+        .filter(|id| seen.insert(*id))
+        .collect()
+}
+
+/// Reads a column written as `Header=Role` into the index of the column with that header and its role
+fn column_role(headers: &[String], known: &[FieldInfo], column: &str) -> Result<(usize, ColumnRole), String> {
+    let Some((header, role)) = column.split_once('=') else {
+        return Err(format!("write columns as Header=Role, not {}", column));
+    };
+    let (header, role) = (header.trim(), role.trim());
+
+    let Some(index) = headers.iter().position(|known| known.eq_ignore_ascii_case(header)) else {
+        return Err(format!("there is no column named {}", header));
+    };
+
+    let role = match role.to_lowercase().as_str() {
+        "skip" => ColumnRole::Skip,
+        "location" => ColumnRole::Location,
+        "kind" => ColumnRole::LocationKind,
+        "name" => ColumnRole::Name,
+        "notes" => ColumnRole::Notes,
+        "quantity" => ColumnRole::Quantity,
+        "tags" => ColumnRole::Tags,
+        _ => match known.iter().find(|info| info.name.eq_ignore_ascii_case(role)) {
+            Some(info) => ColumnRole::Field(info.name.clone()),
+            None => return Err(format!(
+                "{} isn't something a column can hold, pick skip, location, kind, name, notes, quantity, tags or the name of a field",
+                role
+            )),
+        },
+    };
+
+    Ok((index, role))
+}
+
+/// Reads a field written as `Name=Value` into the id of the field and its value
+fn field_value(known: &[FieldInfo], field: &str) -> Result<(i64, String), String> {
+    let Some((name, value)) = field.split_once('=') else {
+        return Err(format!("write fields as Name=Value, not {}", field));
+    };
+    let (name, value) = (name.trim(), value.trim());
+
+    let Some(info) = known.iter().find(|info| info.name.eq_ignore_ascii_case(name)) else {
+        return Err(format!("there is no field named {}", name));
+    };
+    info.check(value)?;
+
+    Ok((info.field_id, value.to_string()))
+}
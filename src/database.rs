@@ -1,232 +1,1880 @@
-use sqlx::{sqlite::SqliteConnectOptions, SqlitePool, Row};
-use std::path::Path;
+use sqlx::{sqlite::{SqliteConnectOptions, SqliteRow}, QueryBuilder, Sqlite, SqliteConnection, SqlitePool, Row};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-use crate::{ItemInfo, Message};
+use crate::{fuzzy, migrations, thumbnail, export::{Export, ExportItem}, import::ImportRow, AddOutcome, CatalogError, Change, FieldFilter, FieldInfo, FieldKind, FieldOp, HistoryAction, HistoryEntry, ItemDetails, ItemInfo, ItemSnapshot, LocationContents, LocationInfo, LocationLevel, LocationSummary, NewItem, PhotoInfo, SearchFilter, SearchHit, SearchMode, Thumbnail};
 
 
+/// An open catalog.
+/// Cloning it is cheap and every clone works on the same catalog.
+#[derive(Debug, Clone)]
+pub struct CatalogStore {
+    pool: SqlitePool,
+}
+
+/// Builds `LocationPath(location_id, path)` containing the full path of every location,
+/// e.g. "Garage / Rack 2 / Shelf 3 / Bin B". Prefix a query with this to join against it.
+const LOCATION_PATHS: &str = "WITH RECURSIVE LocationPath(location_id, path) AS (
+    SELECT location_id, name FROM Location WHERE parent_id IS NULL
+    UNION ALL
+    SELECT Location.location_id, LocationPath.path || ' / ' || Location.name FROM Location JOIN LocationPath ON Location.parent_id = LocationPath.location_id
+)";
+/// The columns read by `item_info`: every column of `Item`, the `path` of its location from `LOCATION_PATHS`,
+/// its `tags` separated by `LIST_SEPARATOR` and its custom `fields` as names and values separated by
+/// `FIELD_SEPARATOR`, each pair separated by `LIST_SEPARATOR`.
+const ITEM_COLUMNS: &str = "Item.*, LocationPath.path, (SELECT group_concat(Tag.name, char(31)) FROM ItemTag JOIN Tag ON Tag.tag_id = ItemTag.tag_id WHERE ItemTag.item_id = Item.item_id) AS tags, (SELECT group_concat(Field.name || char(30) || ItemField.value, char(31)) FROM ItemField JOIN Field ON Field.field_id = ItemField.field_id WHERE ItemField.item_id = Item.item_id) AS fields";
+/// Separates the entries of the `tags` and `fields` columns, `char(31)` in SQL
+const LIST_SEPARATOR: char = '\u{1f}';
+/// Separates the name of a custom field from its value in the `fields` column, `char(30)` in SQL
+const FIELD_SEPARATOR: char = '\u{1e}';
+/// How many entries of the history are loaded at once
+const HISTORY_LIMIT: i64 = 500;
+
+/// The location named `name`, ignoring case, directly inside of `parent`
+async fn find_location(
+    connection: &mut SqliteConnection,
+    parent: Option<i64>,
+    name: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    let existing = sqlx::query("SELECT location_id FROM Location WHERE parent_id IS $1 AND name = $2 COLLATE NOCASE")
+        .bind(parent)
+        .bind(name)
+        .fetch_optional(&mut *connection)
+        .await?;
+
+    Ok(existing.map(|row| row.get::<i64, _>("location_id")))
+}
+
+/// Walks `path` from the root, creating any locations that don't exist yet,
+/// and returns the id of the last one.
+/// A non-empty kind or description replaces the one stored on an existing location,
+/// and new locations without a kind are given the kind "Location".
+async fn find_or_create_location(
+    connection: &mut SqliteConnection,
+    path: &[LocationLevel],
+) -> Result<i64, sqlx::Error> {
+    let mut parent: Option<i64> = None;
+
+    for level in path {
+        let description = if level.description.is_empty() {
+            None
+        } else {
+            Some(level.description.as_str())
+        };
+
+        let location_id = match find_location(&mut *connection, parent, &level.name).await? {
+            Some(location_id) => {
+                if !level.kind.is_empty() {
+                    sqlx::query("UPDATE Location SET kind = $1 WHERE location_id = $2 AND kind IS NOT $1")
+                        .bind(level.kind.as_str())
+                        .bind(location_id)
+                        .execute(&mut *connection)
+                        .await?;
+                }
+                if description.is_some() {
+                    sqlx::query("UPDATE Location SET description = $1 WHERE location_id = $2")
+                        .bind(description)
+                        .bind(location_id)
+                        .execute(&mut *connection)
+                        .await?;
+                }
+                location_id
+            }
+            None => {
+                sqlx::query("INSERT INTO Location (parent_id, kind, name, description) VALUES ($1, $2, $3, $4)")
+                    .bind(parent)
+                    .bind(if level.kind.is_empty() { "Location" } else { level.kind.as_str() })
+                    .bind(level.name.as_str())
+                    .bind(description)
+                    .execute(&mut *connection)
+                    .await?
+                    .last_insert_rowid()
+            }
+        };
 
+        parent = Some(location_id);
+    }
+
+    parent.ok_or(sqlx::Error::Protocol(String::from("an item needs a location")))
+}
+
+/// Refuses an empty location before anything is written, since every item needs one
+fn check_location(location: &[LocationLevel]) -> Result<(), CatalogError> {
+    if location.is_empty() {
+        return Err(CatalogError::Invalid(String::from("an item needs a location")));
+    }
+    Ok(())
+}
+
+fn location_info(row: &SqliteRow) -> LocationInfo {
+    LocationInfo {
+        location_id: row.get("location_id"),
+        kind: row.get("kind"),
+        name: row.get("name"),
+        description: row.get::<Option<String>, _>("description").unwrap_or_default(),
+    }
+}
 
-pub async fn create_database<P: AsRef<Path>>(path: P) -> Message {
-    let options = SqliteConnectOptions::new()
-        .filename(path)
-        .create_if_missing(true);
+fn item_info(row: &SqliteRow) -> ItemInfo {
+    ItemInfo {
+        item_id: row.get("item_id"),
+        location_id: row.get("location_id"),
+        location_path: row.get::<Option<String>, _>("path").unwrap_or_default(),
+        item_name: row.get::<Option<String>, _>("name").unwrap_or_default(),
+        item_notes: row.get::<Option<String>, _>("notes").unwrap_or_default(),
+        quantity: row.get("quantity"),
+        tags: {
+            let mut tags = row.get::<Option<String>, _>("tags")
+                .map(|tags| tags.split(LIST_SEPARATOR).map(String::from).collect::<Vec<_>>())
+                .unwrap_or_default();
+            tags.sort_by_key(|tag| tag.to_lowercase());
+            tags
+        },
+        fields: row.get::<Option<String>, _>("fields")
+            .map(|fields| {
+                fields.split(LIST_SEPARATOR)
+                    .filter_map(|field| field.split_once(FIELD_SEPARATOR))
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        thumbnail: None,
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        deleted_at: row.get("deleted_at"),
+    }
+}
+
+/// Loads a single item by id, `None` if there is no such item
+async fn find_item(
+    connection: &mut SqliteConnection,
+    item_id: i64,
+) -> Result<Option<ItemInfo>, sqlx::Error> {
+    let sql = format!(
+        "{} SELECT {} FROM Item LEFT JOIN LocationPath ON Item.location_id = LocationPath.location_id WHERE Item.item_id = $1",
+        LOCATION_PATHS,
+        ITEM_COLUMNS
+    );
+    let row = sqlx::query(&sql)
+        .bind(item_id)
+        .fetch_optional(&mut *connection)
+        .await?;
+
+    Ok(row.as_ref().map(item_info))
+}
 
-    match SqlitePool::connect_with(options).await {
-        Ok(pool) => {
-            Message::CreateDatabaseSuccess(pool)
+/// The name of the account using the computer, recorded in the history as whoever made a change
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| String::from("unknown"))
+}
+
+/// Appends an entry to the history of changes made to the catalog
+async fn record_history(
+    connection: &mut SqliteConnection,
+    item: &ItemInfo,
+    action: HistoryAction,
+    details: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO History (item_id, item_name, action, details, actor) VALUES ($1, $2, $3, $4, $5)")
+        .bind(item.item_id)
+        .bind(item.item_name.as_str())
+        .bind(action.as_str())
+        .bind(details)
+        .bind(current_user())
+        .execute(&mut *connection)
+        .await?;
+
+    Ok(())
+}
+
+/// Describes what an edit changed about an item for its history, e.g. "renamed from Zelda; quantity 1 to 2"
+fn describe_changes(before: &ItemInfo, after: &ItemInfo) -> String {
+    let mut changes = Vec::new();
+    if before.item_name != after.item_name {
+        changes.push(format!("renamed from {}", before.item_name));
+    }
+    if before.location_path != after.location_path {
+        changes.push(format!("moved from {} to {}", before.location_path, after.location_path));
+    }
+    if before.quantity != after.quantity {
+        changes.push(format!("quantity {} to {}", before.quantity, after.quantity));
+    }
+    if before.item_notes != after.item_notes {
+        changes.push(String::from("notes changed"));
+    }
+    if before.tags != after.tags {
+        changes.push(format!("tags now {}", after.tags.join(", ")));
+    }
+    for (name, value) in after.fields.iter() {
+        let old = before.fields.iter().find(|(old_name, _)| old_name == name);
+        if old.is_none_or(|(_, old_value)| old_value != value) {
+            changes.push(format!("{} set to {}", name, value));
         }
-        Err(err) => {
-            Message::CreateDatabaseFailure(err.to_string())
+    }
+    for (name, _) in before.fields.iter() {
+        if !after.fields.iter().any(|(new_name, _)| new_name == name) {
+            changes.push(format!("{} cleared", name));
         }
     }
+
+    if changes.is_empty() {
+        String::from("saved without changes")
+    } else {
+        changes.join("; ")
+    }
 }
 
-pub async fn open_database<P: AsRef<Path>>(path: P) -> Message {
-    let options = SqliteConnectOptions::new()
-        .filename(path);
+fn thumbnail(row: &SqliteRow) -> Thumbnail {
+    Thumbnail {
+        photo_id: row.get("photo_id"),
+        png: row.get("thumbnail"),
+    }
+}
 
-    match SqlitePool::connect_with(options).await {
-        Ok(pool) => {
-            Message::CreateDatabaseSuccess(pool)
-        }
-        Err(err) => {
-            Message::CreateDatabaseFailure(err.to_string())
+/// Fills in the `thumbnail` of each item from the first photo attached to it.
+/// This is done separately from the main query so that only the items actually being shown load one.
+async fn load_thumbnails(
+    connection: &mut SqliteConnection,
+    items: &mut [ItemInfo],
+) -> Result<(), sqlx::Error> {
+    for item in items.iter_mut() {
+        let row = sqlx::query("SELECT photo_id, thumbnail FROM Photo WHERE item_id = $1 ORDER BY photo_id LIMIT 1")
+            .bind(item.item_id)
+            .fetch_optional(&mut *connection)
+            .await?;
+
+        item.thumbnail = row.as_ref().map(thumbnail);
+    }
+
+    Ok(())
+}
+
+/// Whether the value of one of an item's custom fields passes `filter`
+fn field_matches(value: &str, filter: &FieldFilter) -> bool {
+    let wanted = filter.value.trim();
+
+    if filter.op == FieldOp::Contains {
+        return value.to_lowercase().contains(&wanted.to_lowercase());
+    }
+
+    let ordering = if filter.kind == FieldKind::Number {
+        match (value.parse::<f64>(), wanted.parse::<f64>()) {
+            (Ok(value), Ok(wanted)) => value.partial_cmp(&wanted),
+            _ => None,
         }
+    } else {
+        // dates are stored as YYYY-MM-DD so they sort the same way as text
+        Some(value.to_lowercase().cmp(&wanted.to_lowercase()))
+    };
+
+    match (filter.op, ordering) {
+        (_, None) => false,
+        (FieldOp::Is, Some(ordering)) => ordering.is_eq(),
+        (FieldOp::AtLeast, Some(ordering)) => ordering.is_ge(),
+        (FieldOp::AtMost, Some(ordering)) => ordering.is_le(),
+        (FieldOp::Contains, _) => unreachable!("contains was handled above"),
+    }
+}
+
+/// Whether `item` has every tag in `filter`, ignoring case, and passes every one of its field filters.
+/// Field filters without a value are ignored.
+fn matches_filter(item: &ItemInfo, filter: &SearchFilter) -> bool {
+    let has_tags = filter.tags.iter().all(|tag| {
+        item.tags.iter().any(|item_tag| item_tag.to_lowercase() == tag.to_lowercase())
+    });
+
+    has_tags && filter.fields.iter()
+        .filter(|field| !field.value.trim().is_empty())
+        .all(|field| {
+            item.fields.iter()
+                .find(|(name, _)| name.to_lowercase() == field.name.to_lowercase())
+                .is_some_and(|(_, value)| field_matches(value, field))
+        })
+}
+
+/// Replaces the tags on an item, creating any tags that haven't been used before
+async fn set_tags(
+    connection: &mut SqliteConnection,
+    item_id: i64,
+    tags: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM ItemTag WHERE item_id = $1")
+        .bind(item_id)
+        .execute(&mut *connection)
+        .await?;
+
+    for tag in tags {
+        // tag names ignore case, so "comic" is the same tag as "Comic"
+        sqlx::query("INSERT OR IGNORE INTO Tag (name) VALUES ($1)")
+            .bind(tag.as_str())
+            .execute(&mut *connection)
+            .await?;
+        sqlx::query("INSERT OR IGNORE INTO ItemTag (item_id, tag_id) SELECT $1, tag_id FROM Tag WHERE name = $2")
+            .bind(item_id)
+            .bind(tag.as_str())
+            .execute(&mut *connection)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Replaces the values of an item's custom fields.
+/// Values of number fields are stored as numbers so that they compare as numbers.
+async fn set_fields(
+    connection: &mut SqliteConnection,
+    item_id: i64,
+    fields: &[(i64, String)],
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM ItemField WHERE item_id = $1")
+        .bind(item_id)
+        .execute(&mut *connection)
+        .await?;
+
+    for (field_id, value) in fields {
+        sqlx::query("INSERT INTO ItemField (item_id, field_id, value) SELECT $1, field_id, CASE kind WHEN 'number' THEN $3 + 0 ELSE $3 END FROM Field WHERE field_id = $2")
+            .bind(item_id)
+            .bind(field_id)
+            .bind(value.as_str())
+            .execute(&mut *connection)
+            .await?;
     }
+
+    Ok(())
 }
 
-pub async fn close_database(pool: SqlitePool) -> Message {
-    pool.close().await;
-    Message::ClosedDatabase
+/// Finds an item with the same name as `name`, ignoring case, stored at exactly `location`
+async fn find_identical(
+    connection: &mut SqliteConnection,
+    location: &[LocationLevel],
+    name: &str,
+) -> Result<Option<ItemInfo>, sqlx::Error> {
+    let mut location_id = None;
+    for level in location {
+        location_id = find_location(&mut *connection, location_id, &level.name).await?;
+        if location_id.is_none() {
+            // nothing can be stored in a location that doesn't exist yet
+            return Ok(None);
+        }
+    }
+
+    let sql = format!(
+        "{} SELECT {} FROM Item LEFT JOIN LocationPath ON Item.location_id = LocationPath.location_id WHERE Item.location_id = $1 AND Item.name = $2 COLLATE NOCASE AND Item.deleted_at IS NULL ORDER BY Item.item_id LIMIT 1",
+        LOCATION_PATHS,
+        ITEM_COLUMNS
+    );
+    let row = sqlx::query(&sql)
+        .bind(location_id)
+        .bind(name)
+        .fetch_optional(&mut *connection)
+        .await?;
+
+    Ok(row.as_ref().map(item_info))
 }
 
-pub async fn initialize_database(pool: SqlitePool) -> Message {
+/// Adds an item, creating any part of its location that doesn't exist yet, and returns it.
+/// `note` is added to the end of the entry in the history.
+async fn add_item(
+    connection: &mut SqliteConnection,
+    item: &NewItem,
+    note: &str,
+) -> Result<ItemInfo, sqlx::Error> {
+    let location_id = find_or_create_location(&mut *connection, &item.location).await?;
+
+    let item_id = sqlx::query("INSERT INTO Item (location_id, name, notes, quantity) VALUES ($1, $2, $3, $4)")
+        .bind(location_id)
+        .bind(item.name.as_str())
+        .bind(item.notes.as_str())
+        .bind(item.quantity)
+        .execute(&mut *connection)
+        .await?
+        .last_insert_rowid();
+
+    set_tags(&mut *connection, item_id, &item.tags).await?;
+    set_fields(&mut *connection, item_id, &item.fields).await?;
+
+    let added = find_item(&mut *connection, item_id).await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+
+    let details = format!("to {}{}", added.location_path, note);
+    record_history(&mut *connection, &added, HistoryAction::Added, &details).await?;
+
+    Ok(added)
+}
 
-    match pool.begin().await {
-        Err(err) => {
-            Message::DatabaseTransactionFailure(pool, err.to_string())
+/// Escapes `%`, `_` and `\` so that user input can be used inside of a LIKE pattern with `ESCAPE '\'`
+fn escape_like(query: &str) -> String {
+    let mut escaped = String::with_capacity(query.len());
+    for c in query.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
         }
-        Ok(mut connection) => {
-            let statements = [
-                "CREATE TABLE Rack (rack_id UNSIGNED BIG INT, PRIMARY KEY (rack_id))",
-                "CREATE TABLE Shelf (shelf_id UNSIGNED BIG INT, rack_id UNSIGNED BIG INT, PRIMARY KEY (shelf_id), FOREIGN KEY (rack_id) REFERENCES Rack(rack_id))",
-                "CREATE TABLE Basket (shelf_id UNSIGNED BIG INT, basket_id UNSIGNED BIG INT, PRIMARY KEY (basket_id), FOREIGN KEY (shelf_id) REFERENCES Shelf(shelf_id))",
-                "CREATE TABLE Item (item_id INTEGER PRIMARY KEY AUTOINCREMENT, notes TEXT, name TEXT, rack_id UNSIGNED BIG INT, shelf_id UNSIGNED BIG INT, basket_id UNSIGNED BIG INT, FOREIGN KEY (shelf_id) REFERENCES Shelf(shelf_id) ON DELETE CASCADE, FOREIGN KEY (basket_id) REFERENCES Basket(basket_id) ON DELETE CASCADE, FOREIGN KEY (rack_id) REFERENCES Rack(rack_id) ON DELETE CASCADE)",
-                "CREATE INDEX index_item_name ON Item (name)"
-            ];
+        escaped.push(c);
+    }
+    escaped
+}
 
-            for stmt in statements {
-                let result = sqlx::query(stmt)
-                    .execute(&mut *connection)
-                    .await;
-                match result {
-                    Err(err) => return Message::DatabaseTransactionFailure(pool, err.to_string()),
-                    _ => {}
+/// Marks the start of a matched term in the text of a `SearchHit`, `char(2)` in SQL
+pub const HIGHLIGHT_START: char = '\u{2}';
+/// Marks the end of a matched term in the text of a `SearchHit`, `char(3)` in SQL
+pub const HIGHLIGHT_END: char = '\u{3}';
+
+/// Turns what the user typed into an FTS5 query matching items that contain every word.
+/// Each word is quoted so it is never read as FTS5 syntax, and matches as a prefix.
+fn full_text_query(query: &str) -> String {
+    query.split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Every location on the path to `location_id`, starting from the top level location
+async fn location_ancestors(
+    connection: &mut SqliteConnection,
+    location_id: i64,
+) -> Result<Vec<LocationInfo>, sqlx::Error> {
+    let rows = sqlx::query(
+        "WITH RECURSIVE Ancestor(location_id, parent_id, kind, name, description, depth) AS (
+            SELECT location_id, parent_id, kind, name, description, 0 FROM Location WHERE location_id = $1
+            UNION ALL
+            SELECT Location.location_id, Location.parent_id, Location.kind, Location.name, Location.description, Ancestor.depth + 1 FROM Location JOIN Ancestor ON Location.location_id = Ancestor.parent_id
+        )
+        SELECT * FROM Ancestor ORDER BY depth DESC")
+        .bind(location_id)
+        .fetch_all(&mut *connection)
+        .await?;
+
+    Ok(rows.iter()
+        .map(location_info)
+        .collect())
+}
+
+/// Puts an item in another location, returning where it was before, or `None` if there is no such item.
+/// `note` is added to the end of the entry in the history.
+async fn move_item(
+    connection: &mut SqliteConnection,
+    item_id: i64,
+    location_id: Option<i64>,
+    note: &str,
+) -> Result<Option<Option<i64>>, sqlx::Error> {
+    let Some(before) = find_item(&mut *connection, item_id).await? else {
+        return Ok(None);
+    };
+
+    sqlx::query("UPDATE Item SET location_id = $1 WHERE item_id = $2")
+        .bind(location_id)
+        .bind(item_id)
+        .execute(&mut *connection)
+        .await?;
+
+    if let Some(after) = find_item(&mut *connection, item_id).await? {
+        let details = format!("from {} to {}{}", before.location_path, after.location_path, note);
+        record_history(&mut *connection, &after, HistoryAction::Moved, &details).await?;
+    }
+
+    Ok(Some(before.location_id))
+}
+
+/// The thumbnails of every photo attached to an item, oldest first
+async fn item_photos(
+    connection: &mut SqliteConnection,
+    item_id: i64,
+) -> Result<Vec<PhotoInfo>, sqlx::Error> {
+    let rows = sqlx::query("SELECT photo_id, file_name, thumbnail FROM Photo WHERE item_id = $1 ORDER BY photo_id")
+        .bind(item_id)
+        .fetch_all(&mut *connection)
+        .await?;
+
+    Ok(rows.iter()
+        .map(|row| {
+            PhotoInfo {
+                photo_id: row.get("photo_id"),
+                file_name: row.get("file_name"),
+                thumbnail: thumbnail(row),
+            }
+        })
+        .collect())
+}
+
+/// Adds `by` to the quantity of an item, returning the item afterwards,
+/// or `None` if there is no such item or its quantity would drop below 1.
+/// `note` is added to the end of the entry in the history.
+async fn add_quantity(
+    connection: &mut SqliteConnection,
+    item_id: i64,
+    by: i64,
+    note: &str,
+) -> Result<Option<ItemInfo>, sqlx::Error> {
+    let updated = sqlx::query("UPDATE Item SET quantity = quantity + $1 WHERE item_id = $2 AND quantity + $1 >= 1")
+        .bind(by)
+        .bind(item_id)
+        .execute(&mut *connection)
+        .await?;
+
+    if updated.rows_affected() == 0 {
+        return Ok(None);
+    }
+
+    let item = find_item(&mut *connection, item_id).await?;
+    if let Some(item) = &item {
+        let details = format!("quantity {} to {}{}", item.quantity - by, item.quantity, note);
+        record_history(&mut *connection, item, HistoryAction::Edited, &details).await?;
+    }
+
+    Ok(item)
+}
+
+/// The name, notes, location, tags and fields of an item, so that they can be put back after an edit
+async fn snapshot(
+    connection: &mut SqliteConnection,
+    item_id: i64,
+) -> Result<Option<ItemSnapshot>, sqlx::Error> {
+    let Some(item) = find_item(&mut *connection, item_id).await? else {
+        return Ok(None);
+    };
+
+    // values are read back as text, set_fields turns the values of number fields into numbers again
+    let fields = sqlx::query("SELECT field_id, CAST(value AS TEXT) AS value FROM ItemField WHERE item_id = $1 ORDER BY field_id")
+        .bind(item_id)
+        .fetch_all(&mut *connection)
+        .await?
+        .iter()
+        .map(|row| (row.get("field_id"), row.get("value")))
+        .collect();
+
+    Ok(Some(ItemSnapshot { item, fields }))
+}
+
+/// Puts an item in the trash, returning it,
+/// or `None` if there is no such item or it is already in the trash.
+/// `note` is added to the end of the entry in the history.
+async fn trash_item(
+    connection: &mut SqliteConnection,
+    item_id: i64,
+    note: &str,
+) -> Result<Option<ItemInfo>, sqlx::Error> {
+    let updated = sqlx::query("UPDATE Item SET deleted_at = datetime('now') WHERE item_id = $1 AND deleted_at IS NULL")
+        .bind(item_id)
+        .execute(&mut *connection)
+        .await?;
+
+    if updated.rows_affected() == 0 {
+        return Ok(None);
+    }
+
+    let item = find_item(&mut *connection, item_id).await?;
+    if let Some(item) = &item {
+        let details = format!("to the trash from {}, quantity {}{}", item.location_path, item.quantity, note);
+        record_history(&mut *connection, item, HistoryAction::Deleted, &details).await?;
+    }
+
+    Ok(item)
+}
+
+/// Takes an item back out of the trash, returning it, or `None` if it isn't in the trash.
+/// `note` is added to the end of the entry in the history.
+async fn untrash_item(
+    connection: &mut SqliteConnection,
+    item_id: i64,
+    note: &str,
+) -> Result<Option<ItemInfo>, sqlx::Error> {
+    let updated = sqlx::query("UPDATE Item SET deleted_at = NULL WHERE item_id = $1 AND deleted_at IS NOT NULL")
+        .bind(item_id)
+        .execute(&mut *connection)
+        .await?;
+
+    if updated.rows_affected() == 0 {
+        return Ok(None);
+    }
+
+    let item = find_item(&mut *connection, item_id).await?;
+    if let Some(item) = &item {
+        let details = format!("to {}{}", item.location_path, note);
+        record_history(&mut *connection, item, HistoryAction::Restored, &details).await?;
+    }
+
+    Ok(item)
+}
+
+/// Permanently deletes an item in the trash, returning whether there was one to delete.
+/// `note` is added to the end of the entry in the history.
+async fn purge_item(
+    connection: &mut SqliteConnection,
+    item_id: i64,
+    note: &str,
+) -> Result<bool, sqlx::Error> {
+    let Some(item) = find_item(&mut *connection, item_id).await? else {
+        return Ok(false);
+    };
+    if item.deleted_at.is_none() {
+        return Ok(false);
+    }
+
+    let details = format!("from {}{}", item.location_path, note);
+    record_history(&mut *connection, &item, HistoryAction::Purged, &details).await?;
+
+    sqlx::query("DELETE FROM Item WHERE item_id = $1")
+        .bind(item_id)
+        .execute(&mut *connection)
+        .await?;
+
+    Ok(true)
+}
+
+/// Permanently deletes the items that have been in the trash for more than `days` days
+async fn purge_older_than(
+    connection: &mut SqliteConnection,
+    days: u32,
+) -> Result<(), sqlx::Error> {
+    let expired = sqlx::query("SELECT item_id FROM Item WHERE deleted_at <= datetime('now', $1)")
+        .bind(format!("-{} days", days))
+        .fetch_all(&mut *connection)
+        .await?;
+
+    let note = format!(", after {} days in the trash", days);
+    for row in expired.iter() {
+        purge_item(&mut *connection, row.get("item_id"), &note).await?;
+    }
+
+    Ok(())
+}
+
+/// Puts back the name, notes, location, tags and fields an item had in `earlier`,
+/// returning the ones it had instead, or `None` if there is no such item
+async fn revert_edit(
+    connection: &mut SqliteConnection,
+    earlier: &ItemSnapshot,
+    note: &str,
+) -> Result<Option<ItemSnapshot>, sqlx::Error> {
+    let item = &earlier.item;
+    let Some(before) = snapshot(&mut *connection, item.item_id).await? else {
+        return Ok(None);
+    };
+
+    sqlx::query("UPDATE Item SET location_id = $1, name = $2, notes = $3 WHERE item_id = $4")
+        .bind(item.location_id)
+        .bind(item.item_name.as_str())
+        .bind(item.item_notes.as_str())
+        .bind(item.item_id)
+        .execute(&mut *connection)
+        .await?;
+
+    set_tags(&mut *connection, item.item_id, &item.tags).await?;
+    set_fields(&mut *connection, item.item_id, &earlier.fields).await?;
+
+    if let Some(after) = find_item(&mut *connection, item.item_id).await? {
+        let details = format!("{}{}", describe_changes(&before.item, &after), note);
+        record_history(&mut *connection, &after, HistoryAction::Edited, &details).await?;
+    }
+
+    Ok(Some(before))
+}
+
+/// Does the opposite of `change`, returning the change that does the opposite again
+async fn revert(
+    connection: &mut SqliteConnection,
+    change: Change,
+    note: &str,
+) -> Result<Change, CatalogError> {
+    match change {
+        Change::Added(item) | Change::Restored(item) => {
+            match trash_item(&mut *connection, item.item_id, note).await? {
+                None => Err(CatalogError::Invalid(format!("{} has already been deleted", item.item_name))),
+                Some(item) => Ok(Change::Deleted(item)),
+            }
+        }
+        Change::Edited(snapshot) => {
+            match revert_edit(&mut *connection, &snapshot, note).await? {
+                None => Err(CatalogError::Invalid(format!("{} has been deleted since", snapshot.item.item_name))),
+                Some(before) => Ok(Change::Edited(before)),
+            }
+        }
+        Change::Moved(items) => {
+            let mut moved = Vec::new();
+            for (item_id, location_id) in items {
+                // items deleted since are left out
+                if let Some(from) = move_item(&mut *connection, item_id, location_id, note).await? {
+                    moved.push((item_id, from));
                 }
             }
-            match connection.commit().await {
-                Err(err) => return Message::DatabaseTransactionFailure(pool, err.to_string()),
-                _ => {}
+            if moved.is_empty() {
+                return Err(CatalogError::Invalid(String::from("the moved items have all been deleted since")));
+            }
+            Ok(Change::Moved(moved))
+        }
+        Change::QuantityChanged { item_id, item_name, by } => {
+            match add_quantity(&mut *connection, item_id, -by, note).await? {
+                None => Err(CatalogError::Invalid(format!("{} has been deleted or there are too few of it left", item_name))),
+                Some(item) => Ok(Change::QuantityChanged { item_id, item_name: item.item_name, by: -by }),
+            }
+        }
+        Change::Deleted(item) => {
+            match untrash_item(&mut *connection, item.item_id, note).await? {
+                None => Err(CatalogError::Invalid(format!("{} has been emptied from the trash", item.item_name))),
+                Some(item) => Ok(Change::Restored(item)),
             }
-            Message::DatabaseTransactionSuccess(pool)
         }
     }
 }
 
+impl CatalogStore {
+    /// Creates a new, empty catalog at `path`, or opens the catalog already there
+    pub async fn create<P: AsRef<Path>>(path: P) -> Result<CatalogStore, CatalogError> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
 
-pub async fn insert(
-    pool: SqlitePool,
-    rack: i64,
-    shelf: u64,
-    basket: u64,
-    name: String,
-    notes: String,
-) -> Message {
-    match pool.begin().await {
-        Err(err) => {
-            Message::DatabaseTransactionFailure(pool, err.to_string())
-        }
-        Ok(mut connection) => {
-            let result = sqlx::query("INSERT OR IGNORE INTO Rack (rack_id) VALUES ($1)")
-                .bind(rack.to_string())
-                .execute(&mut *connection)
-                .await;
+        CatalogStore::connect(options).await
+    }
 
-            match result {
-                Err(err) => {
-                    return Message::DatabaseTransactionFailure(pool, err.to_string());
-                }
-                Ok(_) => {}
+    /// Opens the existing catalog at `path`, bringing it up to date with this version of Catalog
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<CatalogStore, CatalogError> {
+        // SQLite only says that it couldn't open the file, so check whether there is one first
+        if !tokio::fs::try_exists(&path).await? {
+            return Err(CatalogError::MissingFile(path.as_ref().to_path_buf()));
+        }
+
+        let options = SqliteConnectOptions::new()
+            .filename(path);
+
+        CatalogStore::connect(options).await
+    }
+
+    /// Connects to the catalog `options` point at and gets it ready for use
+    pub(crate) async fn connect(options: SqliteConnectOptions) -> Result<CatalogStore, CatalogError> {
+        let pool = SqlitePool::connect_with(options).await?;
+
+        let result = async {
+            let mut connection = pool.begin().await?;
+            migrations::prepare(&mut connection).await?;
+            connection.commit().await?;
+            Ok(())
+        }.await;
+
+        match result {
+            Ok(()) => Ok(CatalogStore { pool }),
+            Err(err) => {
+                pool.close().await;
+                Err(err)
             }
+        }
+    }
 
-            let result = sqlx::query("INSERT OR IGNORE INTO Shelf (shelf_id) VALUES ($1)")
-                .bind(shelf.to_string())
-                .execute(&mut *connection)
-                .await;
+    /// Waits for everything being done with the catalog to finish, then closes it
+    pub async fn close(self) {
+        self.pool.close().await;
+    }
 
-            match result {
-                Err(err) => {
-                    return Message::DatabaseTransactionFailure(pool, err.to_string());
+    /// Adds a new item, creating its location if needed.
+    /// Unless `allow_identical` is set, nothing is added when an item with the same name is already
+    /// stored in the same location, and that item is reported back so that its quantity can be bumped instead.
+    pub async fn add_item(
+        &self,
+        item: &NewItem,
+        allow_identical: bool,
+    ) -> Result<AddOutcome, CatalogError> {
+        check_location(&item.location)?;
+
+        let mut connection = self.pool.begin().await?;
+
+        if !allow_identical
+            && let Some(identical) = find_identical(&mut connection, &item.location, &item.name).await? {
+            return Ok(AddOutcome::IdenticalFound(identical));
+        }
+
+        let added = add_item(&mut connection, item, "").await?;
+
+        connection.commit().await?;
+
+        Ok(AddOutcome::Added(added))
+    }
+
+    /// Adds every item in `rows`, along with any locations they need, all at once or not at all.
+    /// `file_name` is the file they were read from, for the history.
+    pub async fn import(
+        &self,
+        rows: &[ImportRow],
+        file_name: &str,
+    ) -> Result<usize, CatalogError> {
+        for row in rows.iter() {
+            check_location(&row.item.location)
+                .map_err(|err| CatalogError::Invalid(format!("line {} couldn't be imported: {}", row.line, err)))?;
+        }
+
+        let mut connection = self.pool.begin().await?;
+
+        let note = format!(", imported from {}", file_name);
+        for row in rows.iter() {
+            if let Err(err) = add_item(&mut connection, &row.item, &note).await {
+                // say which line was at fault, unless the whole catalog was
+                return Err(match CatalogError::from(err) {
+                    CatalogError::ConstraintViolation(err) => {
+                        CatalogError::ConstraintViolation(format!("line {} couldn't be imported: {}", row.line, err))
+                    }
+                    CatalogError::Database(err) => CatalogError::Database(format!("line {} couldn't be imported: {}", row.line, err)),
+                    err => err,
+                });
+            }
+        }
+
+        connection.commit().await?;
+
+        Ok(rows.len())
+    }
+
+    /// Every item that isn't in the trash, along with each level of its location and the names of
+    /// every custom field, ready to be written out
+    pub async fn export(&self) -> Result<Export, CatalogError> {
+        let mut connection = self.pool.begin().await?;
+
+        let sql = format!(
+            "{} SELECT {} FROM Item LEFT JOIN LocationPath ON Item.location_id = LocationPath.location_id WHERE Item.deleted_at IS NULL ORDER BY LocationPath.path, Item.name",
+            LOCATION_PATHS,
+            ITEM_COLUMNS
+        );
+        let items = sqlx::query(&sql)
+            .fetch_all(&mut *connection)
+            .await?
+            .iter()
+            .map(item_info)
+            .collect::<Vec<_>>();
+
+        let locations = sqlx::query("SELECT * FROM Location")
+            .fetch_all(&mut *connection)
+            .await?
+            .iter()
+            .map(|row| (row.get::<i64, _>("location_id"), (row.get::<Option<i64>, _>("parent_id"), location_info(row))))
+            .collect::<HashMap<_, _>>();
+
+        let field_names = sqlx::query("SELECT name FROM Field ORDER BY field_id")
+            .fetch_all(&mut *connection)
+            .await?
+            .iter()
+            .map(|row| row.get("name"))
+            .collect();
+
+        connection.commit().await?;
+
+        let items = items.into_iter()
+            .map(|item| {
+                // walk up from the item's location to the top, then flip it around
+                let mut location = Vec::new();
+                let mut next = item.location_id;
+                while let Some((parent_id, info)) = next.and_then(|location_id| locations.get(&location_id)) {
+                    location.push(LocationLevel {
+                        kind: info.kind.clone(),
+                        name: info.name.clone(),
+                        description: info.description.clone(),
+                    });
+                    next = *parent_id;
                 }
-                Ok(_) => {}
+                location.reverse();
+                ExportItem { item, location }
+            })
+            .collect();
+
+        Ok(Export { items, field_names })
+    }
+
+    /// Searches item names using `mode`, keeping only the items that pass `filter`.
+    /// `SearchMode::FullText` searches the notes too, see `full_text_search` for what matched.
+    pub async fn search(
+        &self,
+        query: &str,
+        mode: SearchMode,
+        filter: &SearchFilter,
+    ) -> Result<Vec<ItemInfo>, CatalogError> {
+        if mode == SearchMode::FullText {
+            let hits = self.full_text_search(query, filter).await?;
+            return Ok(hits.into_iter()
+                .map(|hit| hit.item)
+                .collect());
+        }
+
+        let mut connection = self.pool.acquire().await?;
+
+        let result = match mode {
+            SearchMode::Substring => {
+                // exact matches first, then by how early the query appears in the name
+                let sql = format!(
+                    "{} SELECT {} FROM Item LEFT JOIN LocationPath ON Item.location_id = LocationPath.location_id WHERE Item.name LIKE $1 ESCAPE '\\' AND Item.deleted_at IS NULL ORDER BY lower(Item.name) = lower($2) DESC, instr(lower(Item.name), lower($2)), length(Item.name), Item.name",
+                    LOCATION_PATHS,
+                    ITEM_COLUMNS
+                );
+                sqlx::query(&sql)
+                    .bind(format!("%{}%", escape_like(query)))
+                    .bind(query)
+                    .fetch_all(&mut *connection)
+                    .await?
+            }
+            SearchMode::Prefix => {
+                let sql = format!(
+                    "{} SELECT {} FROM Item LEFT JOIN LocationPath ON Item.location_id = LocationPath.location_id WHERE Item.name LIKE $1 ESCAPE '\\' AND Item.deleted_at IS NULL ORDER BY length(Item.name), Item.name",
+                    LOCATION_PATHS,
+                    ITEM_COLUMNS
+                );
+                sqlx::query(&sql)
+                    .bind(format!("{}%", escape_like(query)))
+                    .fetch_all(&mut *connection)
+                    .await?
             }
+            SearchMode::FullText => unreachable!("full text searches are handled by full_text_search"),
+            SearchMode::Fuzzy => {
+                // SQLite can't score typos, so every item is scored in fuzzy::substring_distance below
+                let sql = format!(
+                    "{} SELECT {} FROM Item LEFT JOIN LocationPath ON Item.location_id = LocationPath.location_id WHERE Item.deleted_at IS NULL",
+                    LOCATION_PATHS,
+                    ITEM_COLUMNS
+                );
+                sqlx::query(&sql)
+                    .fetch_all(&mut *connection)
+                    .await?
+            }
+        };
 
-            let result = sqlx::query("INSERT OR IGNORE INTO Basket (basket_id) VALUES ($1)")
-                .bind(basket.to_string())
-                .execute(&mut *connection)
-                .await;
+        let mut result = match mode {
+            SearchMode::Fuzzy => {
+                let tolerance = fuzzy::tolerance(query);
+                let mut scored = result.iter()
+                    .map(item_info)
+                    .filter(|item| matches_filter(item, filter))
+                    .map(|item| (fuzzy::substring_distance(query, &item.item_name), item))
+                    .filter(|(distance, _)| *distance <= tolerance)
+                    .collect::<Vec<_>>();
+                scored.sort_by(|(left_distance, left), (right_distance, right)| {
+                    left_distance.cmp(right_distance)
+                        .then(left.item_name.len().cmp(&right.item_name.len()))
+                        .then(left.item_name.cmp(&right.item_name))
+                });
+                scored.into_iter()
+                    .map(|(_, item)| item)
+                    .collect::<Vec<_>>()
+            }
+            _ => {
+                result.iter()
+                    .map(item_info)
+                    .filter(|item| matches_filter(item, filter))
+                    .collect::<Vec<_>>()
+            }
+        };
 
-            match result {
-                Err(err) => {
-                    return Message::DatabaseTransactionFailure(pool, err.to_string());
+        load_thumbnails(&mut connection, &mut result).await?;
+
+        Ok(result)
+    }
+
+    /// Finds the items stored anywhere within the locations matching `location`, optionally also
+    /// requiring the name to contain `name` and the item to pass `filter`. Each entry of `location` filters one level of the path
+    /// from the top down, matching names while ignoring case, and an empty entry matches any location at that level,
+    /// so `["Rack 1", "", "Basket 4"]` finds everything in any "Basket 4" on any shelf of "Rack 1".
+    pub async fn search_by_location(
+        &self,
+        location: &[String],
+        name: &str,
+        filter: &SearchFilter,
+    ) -> Result<Vec<ItemInfo>, CatalogError> {
+        let mut connection = self.pool.acquire().await?;
+
+        let rows = sqlx::query("SELECT location_id, parent_id, name FROM Location")
+            .fetch_all(&mut *connection)
+            .await?;
+
+        let mut children: HashMap<Option<i64>, Vec<(i64, String)>> = HashMap::new();
+        for row in rows.iter() {
+            children.entry(row.get("parent_id"))
+                .or_default()
+                .push((row.get("location_id"), row.get("name")));
+        }
+
+        // walk down the tree one filter level at a time
+        let mut matched = vec![None];
+        for filter in location.iter() {
+            let filter = filter.trim().to_lowercase();
+            matched = matched.iter()
+                .flat_map(|parent| children.get(parent).into_iter().flatten())
+                .filter(|(_, name)| filter.is_empty() || name.to_lowercase() == filter)
+                .map(|(location_id, _)| Some(*location_id))
+                .collect();
+        }
+
+        // then take in everything below the matched locations
+        let mut within = Vec::new();
+        while let Some(location_id) = matched.pop() {
+            if let Some(location_id) = location_id {
+                within.push(location_id);
+            }
+            if let Some(inside) = children.get(&location_id) {
+                matched.extend(inside.iter().map(|(location_id, _)| Some(*location_id)));
+            }
+        }
+
+        if within.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut query = QueryBuilder::<Sqlite>::new(format!("{} SELECT {}", LOCATION_PATHS, ITEM_COLUMNS));
+        query.push(" FROM Item LEFT JOIN LocationPath ON Item.location_id = LocationPath.location_id WHERE Item.name LIKE ");
+        query.push_bind(format!("%{}%", escape_like(name.trim())));
+        query.push(" ESCAPE '\\' AND Item.deleted_at IS NULL AND Item.location_id IN (");
+        let mut ids = query.separated(", ");
+        for location_id in within {
+            ids.push_bind(location_id);
+        }
+        query.push(") ORDER BY LocationPath.path, Item.name");
+
+        let mut result = query.build()
+            .fetch_all(&mut *connection)
+            .await?
+            .iter()
+            .map(item_info)
+            .filter(|item| matches_filter(item, filter))
+            .collect::<Vec<_>>();
+
+        load_thumbnails(&mut connection, &mut result).await?;
+
+        Ok(result)
+    }
+
+    /// Searches item names and notes together using the `ItemSearch` full text index,
+    /// best matches first, keeping only the items that pass `filter`.
+    pub async fn full_text_search(
+        &self,
+        query: &str,
+        filter: &SearchFilter,
+    ) -> Result<Vec<SearchHit>, CatalogError> {
+        let query = full_text_query(query);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut connection = self.pool.acquire().await?;
+
+        let sql = format!(
+            "{} SELECT {}, highlight(ItemSearch, 0, char(2), char(3)) AS name_highlight, snippet(ItemSearch, 1, char(2), char(3), '...', 12) AS notes_snippet FROM ItemSearch JOIN Item ON Item.item_id = ItemSearch.rowid LEFT JOIN LocationPath ON Item.location_id = LocationPath.location_id WHERE ItemSearch MATCH $1 AND Item.deleted_at IS NULL ORDER BY ItemSearch.rank",
+            LOCATION_PATHS,
+            ITEM_COLUMNS
+        );
+        let mut result = sqlx::query(&sql)
+            .bind(query)
+            .fetch_all(&mut *connection)
+            .await?
+            .iter()
+            .map(|row| {
+                SearchHit {
+                    item: item_info(row),
+                    name: row.get("name_highlight"),
+                    notes: row.get::<Option<String>, _>("notes_snippet")
+                        .filter(|snippet| !snippet.is_empty()),
+                }
+            })
+            .filter(|hit| matches_filter(&hit.item, filter))
+            .collect::<Vec<_>>();
+
+        for hit in result.iter_mut() {
+            load_thumbnails(&mut connection, std::slice::from_mut(&mut hit.item)).await?;
+        }
+
+        Ok(result)
+    }
+
+    /// Rebuilds the full text index from the `Item` table, for catalogs whose index has gone stale
+    pub async fn rebuild_search_index(&self) -> Result<(), CatalogError> {
+        sqlx::query("INSERT INTO ItemSearch (ItemSearch) VALUES ('rebuild')")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Every tag that is on at least one item, for picking from
+    pub async fn tag_names(&self) -> Result<Vec<String>, CatalogError> {
+        let rows = sqlx::query("SELECT name FROM Tag WHERE tag_id IN (SELECT tag_id FROM ItemTag JOIN Item ON Item.item_id = ItemTag.item_id WHERE Item.deleted_at IS NULL) ORDER BY name")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter()
+            .map(|row| row.get("name"))
+            .collect())
+    }
+
+    /// Every custom field declared for this catalog, in the order they were added
+    pub async fn fields(&self) -> Result<Vec<FieldInfo>, CatalogError> {
+        let rows = sqlx::query("SELECT * FROM Field ORDER BY field_id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter()
+            .map(|row| {
+                FieldInfo {
+                    field_id: row.get("field_id"),
+                    name: row.get("name"),
+                    kind: FieldKind::from_name(row.get("kind")),
+                    choices: row.get::<Option<String>, _>("choices")
+                        .map(|choices| choices.lines().map(String::from).collect())
+                        .unwrap_or_default(),
                 }
-                Ok(_) => {}
+            })
+            .collect())
+    }
+
+    /// Declares a new custom field that every item in the catalog can have a value for.
+    /// `choices` lists the values a choice field can take and is ignored for other kinds.
+    pub async fn create_field(
+        &self,
+        name: &str,
+        kind: FieldKind,
+        choices: &[String],
+    ) -> Result<(), CatalogError> {
+        let choices = if kind == FieldKind::Choice {
+            Some(choices.join("\n"))
+        } else {
+            None
+        };
+
+        sqlx::query("INSERT INTO Field (name, kind, choices) VALUES ($1, $2, $3)")
+            .bind(name)
+            .bind(kind.as_str())
+            .bind(choices)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Removes a custom field along with the value every item had for it
+    pub async fn delete_field(&self, field_id: i64) -> Result<(), CatalogError> {
+        sqlx::query("DELETE FROM Field WHERE field_id = $1")
+            .bind(field_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Looks up the locations that can be picked at each level of `path`:
+    /// the top level locations for the first level, then the locations inside of the one named at the level above.
+    /// Levels below a location that doesn't exist yet have nothing to pick.
+    pub async fn location_choices(&self, path: &[String]) -> Result<Vec<Vec<LocationInfo>>, CatalogError> {
+        let mut connection = self.pool.acquire().await?;
+
+        let mut choices = Vec::with_capacity(path.len());
+        let mut parent = None;
+        for (index, name) in path.iter().enumerate() {
+            if index > 0 && parent.is_none() {
+                choices.push(Vec::new());
+                continue;
             }
 
-            let result = sqlx::query("INSERT INTO Item (rack_id, shelf_id, basket_id, name, notes) VALUES ($1, $2, $3, $4, $5)")
-                .bind(rack.to_string())
-                .bind(shelf.to_string())
-                .bind(basket.to_string())
-                .bind(name.to_string())
-                .bind(notes.to_string())
-                .execute(&mut *connection)
-                .await;
+            let level_choices = sqlx::query("SELECT * FROM Location WHERE parent_id IS $1 ORDER BY name")
+                .bind(parent)
+                .fetch_all(&mut *connection)
+                .await?
+                .iter()
+                .map(location_info)
+                .collect::<Vec<_>>();
 
-            match result {
-                Err(err) => {
-                    return Message::DatabaseTransactionFailure(pool, err.to_string());
+            parent = level_choices.iter()
+                .find(|choice| choice.name == *name)
+                .map(|choice| choice.location_id);
+            choices.push(level_choices);
+        }
+
+        Ok(choices)
+    }
+
+    /// Loads a single item along with the full path to where it is stored and its photos
+    pub async fn load_item(&self, item_id: i64) -> Result<ItemDetails, CatalogError> {
+        let mut connection = self.pool.begin().await?;
+
+        let mut item = find_item(&mut connection, item_id).await?
+            .ok_or(CatalogError::NoSuchItem(item_id))?;
+
+        load_thumbnails(&mut connection, std::slice::from_mut(&mut item)).await?;
+
+        let photos = item_photos(&mut connection, item_id).await?;
+
+        let location = match item.location_id {
+            None => Vec::new(),
+            Some(location_id) => location_ancestors(&mut connection, location_id).await?
+                .into_iter()
+                .map(|location| LocationLevel {
+                    kind: location.kind,
+                    name: location.name,
+                    description: location.description,
+                })
+                .collect(),
+        };
+
+        Ok(ItemDetails { item, location, photos })
+    }
+
+    /// Lists what is inside of a location: the path leading to it,
+    /// the locations directly inside of it with how many items each holds in total,
+    /// and the items stored directly in it.
+    /// `None` lists the top level locations along with any items that have no location.
+    pub async fn browse(&self, location_id: Option<i64>) -> Result<LocationContents, CatalogError> {
+        let mut connection = self.pool.begin().await?;
+
+        let trail = match location_id {
+            None => Vec::new(),
+            Some(location_id) => location_ancestors(&mut connection, location_id).await?,
+        };
+
+        let children = sqlx::query(
+            "WITH RECURSIVE Descendant(root_id, location_id) AS (
+                SELECT location_id, location_id FROM Location WHERE parent_id IS $1
+                UNION ALL
+                SELECT Descendant.root_id, Location.location_id FROM Location JOIN Descendant ON Location.parent_id = Descendant.location_id
+            )
+            SELECT Location.*, (SELECT COUNT(*) FROM Item JOIN Descendant ON Item.location_id = Descendant.location_id WHERE Descendant.root_id = Location.location_id AND Item.deleted_at IS NULL) AS item_count FROM Location WHERE parent_id IS $1 ORDER BY name")
+            .bind(location_id)
+            .fetch_all(&mut *connection)
+            .await?
+            .iter()
+            .map(|row| {
+                LocationSummary {
+                    location: location_info(row),
+                    item_count: row.get("item_count"),
                 }
-                Ok(_) => {}
+            })
+            .collect();
+
+        let sql = format!(
+            "{} SELECT {} FROM Item LEFT JOIN LocationPath ON Item.location_id = LocationPath.location_id WHERE Item.location_id IS $1 AND Item.deleted_at IS NULL ORDER BY Item.name",
+            LOCATION_PATHS,
+            ITEM_COLUMNS
+        );
+        let mut items = sqlx::query(&sql)
+            .bind(location_id)
+            .fetch_all(&mut *connection)
+            .await?
+            .iter()
+            .map(item_info)
+            .collect::<Vec<_>>();
+
+        load_thumbnails(&mut connection, &mut items).await?;
+
+        Ok(LocationContents { trail, children, items })
+    }
+
+    /// Replaces the name, notes, location, tags and custom fields of an existing item,
+    /// returning what it was like before so the edit can be undone
+    pub async fn update_item(
+        &self,
+        item_id: i64,
+        location: &[LocationLevel],
+        name: &str,
+        notes: &str,
+        tags: &[String],
+        fields: &[(i64, String)],
+    ) -> Result<ItemSnapshot, CatalogError> {
+        check_location(location)?;
+
+        let mut connection = self.pool.begin().await?;
+
+        let before = snapshot(&mut connection, item_id).await?
+            .ok_or(CatalogError::NoSuchItem(item_id))?;
+
+        let location_id = find_or_create_location(&mut connection, location).await?;
+
+        let updated = sqlx::query("UPDATE Item SET location_id = $1, name = $2, notes = $3 WHERE item_id = $4")
+            .bind(location_id)
+            .bind(name)
+            .bind(notes)
+            .bind(item_id)
+            .execute(&mut *connection)
+            .await?;
+
+        if updated.rows_affected() == 0 {
+            return Err(CatalogError::NoSuchItem(item_id));
+        }
+
+        set_tags(&mut connection, item_id, tags).await?;
+        set_fields(&mut connection, item_id, fields).await?;
+
+        if let Some(after) = find_item(&mut connection, item_id).await? {
+            let details = describe_changes(&before.item, &after);
+            record_history(&mut connection, &after, HistoryAction::Edited, &details).await?;
+        }
+
+        connection.commit().await?;
+
+        Ok(before)
+    }
+
+    /// Moves every item in `item_ids` into `location` in one transaction, creating the location if needed.
+    /// Returns each item that was actually moved along with the location it was moved from,
+    /// leaving out items in the trash, which stay where they were so that restoring them puts them back there.
+    pub async fn move_items(
+        &self,
+        item_ids: &[i64],
+        location: &[LocationLevel],
+    ) -> Result<Vec<(i64, Option<i64>)>, CatalogError> {
+        check_location(location)?;
+
+        let mut connection = self.pool.begin().await?;
+
+        let location_id = find_or_create_location(&mut connection, location).await?;
+
+        let mut moved = Vec::new();
+        for item_id in item_ids.iter().copied() {
+            let trashed = find_item(&mut connection, item_id).await?
+                .is_none_or(|item| item.deleted_at.is_some());
+            if trashed {
+                continue;
+            }
+            if let Some(from) = move_item(&mut connection, item_id, Some(location_id), "").await? {
+                moved.push((item_id, from));
             }
+        }
 
-            let result = connection.commit().await;
+        connection.commit().await?;
+
+        Ok(moved)
+    }
+
+    /// The photos attached to an item, oldest first
+    pub async fn photos(&self, item_id: i64) -> Result<Vec<PhotoInfo>, CatalogError> {
+        let mut connection = self.pool.acquire().await?;
+
+        Ok(item_photos(&mut connection, item_id).await?)
+    }
+
+    /// Copies the image files at `paths` into the catalog as photos of an item, along with a thumbnail of each.
+    /// Nothing is attached if any of the files can't be read as an image.
+    pub async fn attach_photos(
+        &self,
+        item_id: i64,
+        paths: &[PathBuf],
+    ) -> Result<(), CatalogError> {
+        let mut photos = Vec::with_capacity(paths.len());
+        for path in paths {
+            let file_name = path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            let photo = tokio::fs::read(&path).await
+                .map_err(|err| CatalogError::Io(format!("Could not read {}: {}", path.display(), err)))?;
+
+            // decoding and scaling a large photo takes a while, so keep it off of the async threads
+            let result = tokio::task::spawn_blocking(move || {
+                thumbnail::make(&photo).map(|thumbnail| (photo, thumbnail))
+            }).await;
 
             match result {
+                Ok(Ok((photo, thumbnail))) => photos.push((file_name, photo, thumbnail)),
+                Ok(Err(err)) => {
+                    return Err(CatalogError::Invalid(format!("Could not read {} as an image: {}", path.display(), err)));
+                }
                 Err(err) => {
-                    return Message::DatabaseTransactionFailure(pool, err.to_string());
+                    return Err(CatalogError::Invalid(err.to_string()));
                 }
-                Ok(_) => {}
             }
+        }
+
+        let mut connection = self.pool.begin().await?;
 
-            Message::DatabaseTransactionSuccess(pool)
+        for (file_name, photo, thumbnail) in photos {
+            sqlx::query("INSERT INTO Photo (item_id, file_name, image, thumbnail) VALUES ($1, $2, $3, $4)")
+                .bind(item_id)
+                .bind(file_name)
+                .bind(photo)
+                .bind(thumbnail)
+                .execute(&mut *connection)
+                .await?;
         }
+
+        connection.commit().await?;
+
+        Ok(())
     }
-}
 
-pub async fn search(
-    pool: SqlitePool,
-    name: String
-) -> Message {
-    match pool.begin().await {
-        Err(err) => {
-            Message::DatabaseTransactionFailure(pool, err.to_string())
-        }
-        Ok(mut connection) => {
-            let result = sqlx::query("SELECT * FROM Item INDEXED BY index_item_name WHERE name = $1")
-                .bind(name)
-                .fetch_all(&mut *connection)
-                .await;
+    /// Removes a single photo from the item it is attached to
+    pub async fn delete_photo(
+        &self,
+        item_id: i64,
+        photo_id: i64,
+    ) -> Result<(), CatalogError> {
+        sqlx::query("DELETE FROM Photo WHERE photo_id = $1 AND item_id = $2")
+            .bind(photo_id)
+            .bind(item_id)
+            .execute(&self.pool)
+            .await?;
 
-            let result = match result {
-                Err(err) => {
-                    return Message::DatabaseTransactionFailure(pool, err.to_string());
-                }
-                Ok(result) => result,
-            };
-
-            if result.len() == 0 {
-                Message::DatabaseSearchFailure(pool)
-            } else {
-
-                let result = result.into_iter()
-                    .map(|row| {
-                        ItemInfo {
-                            rack_number: row.get::<i64,_>("rack_id").to_string(),
-                            shelf_number: row.get::<u64,_>("shelf_id").to_string(),
-                            basket_number: row.get::<u64,_>("basket_id").to_string(),
-                            item_name: row.get("name"),
-                            item_notes: row.get("notes"),
-                        }
-                    })
-                    .collect::<Vec<_>>();
-                Message::DatabaseSearchSuccess(pool, result)
+        Ok(())
+    }
+
+    /// Changes how many of an item there are by `by`, which is negative to take some away, returning the item afterwards.
+    /// The quantity never drops below 1, the item has to be deleted to get rid of the last one.
+    pub async fn change_quantity(
+        &self,
+        item_id: i64,
+        by: i64,
+    ) -> Result<ItemInfo, CatalogError> {
+        let mut connection = self.pool.begin().await?;
+
+        let item = add_quantity(&mut connection, item_id, by, "").await?
+            .ok_or_else(|| CatalogError::Invalid(format!("item {} can't have its quantity changed by {}", item_id, by)))?;
+
+        connection.commit().await?;
+
+        Ok(item)
+    }
+
+    /// Puts the single item with `item_id` in the trash and returns it,
+    /// or `None` if it was already gone
+    pub async fn delete_item(&self, item_id: i64) -> Result<Option<ItemInfo>, CatalogError> {
+        let mut connection = self.pool.begin().await?;
+
+        let deleted = trash_item(&mut connection, item_id, "").await?;
+
+        connection.commit().await?;
+
+        Ok(deleted)
+    }
+
+    /// Puts every item in `item_ids` in the trash in one transaction and returns them.
+    /// Nothing is changed if any of them doesn't exist or is already in the trash.
+    pub async fn delete_items(&self, item_ids: &[i64]) -> Result<Vec<ItemInfo>, CatalogError> {
+        let mut connection = self.pool.begin().await?;
+
+        let mut deleted = Vec::new();
+        for item_id in item_ids.iter().copied() {
+            match trash_item(&mut connection, item_id, "").await? {
+                Some(item) => deleted.push(item),
+                None => return Err(match find_item(&mut connection, item_id).await? {
+                    None => CatalogError::NoSuchItem(item_id),
+                    Some(item) => CatalogError::Invalid(format!("{} ({}) is already in the trash", item.item_name, item_id)),
+                }),
             }
         }
+
+        connection.commit().await?;
+
+        Ok(deleted)
     }
-}
 
-pub async fn delete(
-    pool: SqlitePool,
-    name: String
-) -> Message {
-    match pool.begin().await {
-        Err(err) => {
-            Message::DatabaseTransactionFailure(pool, err.to_string())
+    /// Takes the single item with `item_id` back out of the trash and returns it,
+    /// or `None` if it isn't in the trash
+    pub async fn restore_item(&self, item_id: i64) -> Result<Option<ItemInfo>, CatalogError> {
+        let mut connection = self.pool.begin().await?;
+
+        let restored = untrash_item(&mut connection, item_id, "").await?;
+
+        connection.commit().await?;
+
+        Ok(restored)
+    }
+
+    /// Permanently deletes the items with `item_ids` that are in the trash, along with their tags, fields and photos,
+    /// returning how many were deleted
+    pub async fn purge(&self, item_ids: &[i64]) -> Result<u64, CatalogError> {
+        let mut connection = self.pool.begin().await?;
+
+        let mut purged = 0;
+        for item_id in item_ids.iter().copied() {
+            if purge_item(&mut connection, item_id, "").await? {
+                purged += 1;
+            }
         }
-        Ok(mut connection) => {
 
-            let result = sqlx::query("DELETE FROM Item where name = $1")
-                .bind(name.to_string())
-                .execute(&mut *connection)
-                .await;
+        connection.commit().await?;
 
-            match result {
-                Err(err) => {
-                    return Message::DatabaseTransactionFailure(pool, err.to_string());
-                }
-                Ok(_) => {}
+        Ok(purged)
+    }
+
+    /// Permanently deletes every item in `item_ids` in one transaction, whether or not it is in the trash,
+    /// along with its tags, fields and photos, and returns them as they were.
+    /// Nothing is deleted if any of them doesn't exist.
+    pub async fn purge_items(&self, item_ids: &[i64]) -> Result<Vec<ItemInfo>, CatalogError> {
+        let mut connection = self.pool.begin().await?;
+
+        let mut purged = Vec::new();
+        for item_id in item_ids.iter().copied() {
+            let item = find_item(&mut connection, item_id).await?
+                .ok_or(CatalogError::NoSuchItem(item_id))?;
+            // only items in the trash can be purged, so the rest pass through it on the way out
+            if item.deleted_at.is_none() {
+                trash_item(&mut connection, item_id, "").await?;
             }
+            purge_item(&mut connection, item_id, "").await?;
+            purged.push(item);
+        }
 
-            let result = connection.commit().await;
+        connection.commit().await?;
 
-            match result {
-                Err(err) => {
-                    return Message::DatabaseTransactionFailure(pool, err.to_string());
+        Ok(purged)
+    }
+
+    /// Permanently deletes the items that have been in the trash for more than `days` days
+    pub async fn purge_expired(&self, days: u32) -> Result<(), CatalogError> {
+        let mut connection = self.pool.begin().await?;
+
+        purge_older_than(&mut connection, days).await?;
+
+        connection.commit().await?;
+
+        Ok(())
+    }
+
+    /// The items in the trash, the most recently deleted first.
+    /// When `purge_after` is set, the items that have been in the trash for longer than that many days are purged first.
+    pub async fn trash(&self, purge_after: Option<u32>) -> Result<Vec<ItemInfo>, CatalogError> {
+        let mut connection = self.pool.begin().await?;
+
+        if let Some(days) = purge_after {
+            purge_older_than(&mut connection, days).await?;
+        }
+
+        let sql = format!(
+            "{} SELECT {} FROM Item LEFT JOIN LocationPath ON Item.location_id = LocationPath.location_id WHERE Item.deleted_at IS NOT NULL ORDER BY Item.deleted_at DESC, Item.name",
+            LOCATION_PATHS,
+            ITEM_COLUMNS
+        );
+        let items = sqlx::query(&sql)
+            .fetch_all(&mut *connection)
+            .await?
+            .iter()
+            .map(item_info)
+            .collect();
+
+        connection.commit().await?;
+
+        Ok(items)
+    }
+
+    /// Reverts `change` in a transaction of its own
+    async fn revert_change(
+        &self,
+        change: Change,
+        note: &str,
+    ) -> Result<Change, CatalogError> {
+        let mut connection = self.pool.begin().await?;
+
+        let reverted = revert(&mut connection, change, note).await?;
+
+        connection.commit().await?;
+
+        Ok(reverted)
+    }
+
+    /// Undoes `change`, returning the change that redoes it
+    pub async fn undo(&self, change: Change) -> Result<Change, CatalogError> {
+        self.revert_change(change, " (undone)").await
+    }
+
+    /// Redoes a change that was undone, where `change` is what `undo` returned,
+    /// returning the change that undoes it again
+    pub async fn redo(&self, change: Change) -> Result<Change, CatalogError> {
+        self.revert_change(change, " (redone)").await
+    }
+
+    /// The most recent changes made to the catalog, newest first:
+    /// those made to one item when `item_id` is given, otherwise the latest `HISTORY_LIMIT` changes to any item
+    pub async fn history(&self, item_id: Option<i64>) -> Result<Vec<HistoryEntry>, CatalogError> {
+        let rows = sqlx::query("SELECT * FROM History WHERE $1 IS NULL OR item_id = $1 ORDER BY history_id DESC LIMIT $2")
+            .bind(item_id)
+            .bind(HISTORY_LIMIT)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter()
+            .map(|row| {
+                HistoryEntry {
+                    item_id: row.get("item_id"),
+                    item_name: row.get("item_name"),
+                    action: HistoryAction::from_name(row.get("action")),
+                    details: row.get("details"),
+                    actor: row.get("actor"),
+                    happened_at: row.get("happened_at"),
                 }
-                Ok(_) => {}
-            }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CatalogStore;
+    use crate::{export, import::{self, ColumnRole}, AddOutcome, CatalogError, Change, HistoryAction, ItemInfo, LocationLevel, NewItem, SearchFilter, SearchMode};
 
-            Message::DatabaseTransactionSuccess(pool)
+    /// A new catalog that lives in memory for as long as the store does
+    async fn memory() -> CatalogStore {
+        CatalogStore::connect("sqlite::memory:".parse().unwrap()).await.unwrap()
+    }
+
+    fn location(path: &[(&str, &str)]) -> Vec<LocationLevel> {
+        path.iter()
+            .map(|(kind, name)| LocationLevel {
+                kind: kind.to_string(),
+                name: name.to_string(),
+                description: String::new(),
+            })
+            .collect()
+    }
+
+    fn new_item(name: &str, path: &[(&str, &str)]) -> NewItem {
+        NewItem {
+            location: location(path),
+            name: name.to_string(),
+            notes: String::new(),
+            quantity: 1,
+            tags: Vec::new(),
+            fields: Vec::new(),
         }
     }
-}
\ No newline at end of file
+
+    async fn add(store: &CatalogStore, name: &str, path: &[(&str, &str)]) -> ItemInfo {
+        match store.add_item(&new_item(name, path), true).await.unwrap() {
+            AddOutcome::Added(item) => item,
+            AddOutcome::IdenticalFound(item) => panic!("{} was already there", item.item_name),
+        }
+    }
+
+    #[tokio::test]
+    async fn items_need_a_location() {
+        let store = memory().await;
+        let lamp = add(&store, "Lamp", &[("Room", "Garage")]).await;
+        let invalid = Err(CatalogError::Invalid(String::from("an item needs a location")));
+
+        assert_eq!(store.add_item(&new_item("Tent", &[]), false).await.map(|_| ()), invalid);
+        assert_eq!(store.update_item(lamp.item_id, &[], "Lamp", "", &[], &[]).await.map(|_| ()), invalid);
+        assert_eq!(store.move_items(&[lamp.item_id], &[]).await.map(|_| ()), invalid);
+
+        // and the lamp is still where it was
+        assert_eq!(store.load_item(lamp.item_id).await.unwrap().item.location_path, "Garage");
+    }
+
+    #[tokio::test]
+    async fn location_names_ignore_case() {
+        let store = memory().await;
+        let lamp = add(&store, "Lamp", &[("Room", "Garage"), ("Shelf", "Top")]).await;
+        let tent = add(&store, "Tent", &[("Room", "garage"), ("Shelf", "TOP")]).await;
+
+        assert_eq!(lamp.location_id, tent.location_id);
+        assert_eq!(tent.location_path, "Garage / Top");
+        assert_eq!(store.browse(None).await.unwrap().children.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn given_kinds_replace_stored_ones() {
+        let store = memory().await;
+        add(&store, "Lamp", &[("Room", "Garage")]).await;
+        let kind = async |store: &CatalogStore| store.browse(None).await.unwrap().children[0].location.kind.clone();
+
+        // no kind keeps the one already there
+        add(&store, "Tent", &[("", "garage")]).await;
+        assert_eq!(kind(&store).await, "Room");
+
+        add(&store, "Stove", &[("Shed", "Garage")]).await;
+        assert_eq!(kind(&store).await, "Shed");
+
+        // and new locations without one are plain locations
+        let sock = add(&store, "Sock", &[("", "Attic")]).await;
+        let attic = store.browse(None).await.unwrap().children.into_iter()
+            .find(|child| Some(child.location.location_id) == sock.location_id)
+            .unwrap();
+        assert_eq!(attic.location.kind, "Location");
+    }
+
+    /// What an item is called, where it is, how many there are and whether it is in the trash
+    async fn state(store: &CatalogStore, item_id: i64) -> (String, String, i64, bool) {
+        let item = store.load_item(item_id).await.unwrap().item;
+        (item.item_name, item.location_path, item.quantity, item.deleted_at.is_some())
+    }
+
+    fn expect(name: &str, path: &str, quantity: i64, deleted: bool) -> (String, String, i64, bool) {
+        (name.to_string(), path.to_string(), quantity, deleted)
+    }
+
+    /// Undoes `change` and redoes it again, checking the item against `before` and `after` each time
+    async fn undo_and_redo(
+        store: &CatalogStore,
+        item_id: i64,
+        change: Change,
+        before: (String, String, i64, bool),
+        after: (String, String, i64, bool),
+    ) {
+        assert_eq!(state(store, item_id).await, after);
+        let redo = store.undo(change).await.unwrap();
+        assert_eq!(state(store, item_id).await, before);
+        let undo = store.redo(redo).await.unwrap();
+        assert_eq!(state(store, item_id).await, after);
+        // and it can be undone once more
+        store.undo(undo).await.unwrap();
+        assert_eq!(state(store, item_id).await, before);
+    }
+
+    #[tokio::test]
+    async fn added_items_can_be_undone_and_redone() {
+        let store = memory().await;
+        let lamp = add(&store, "Lamp", &[("Room", "Garage")]).await;
+
+        let change = Change::Added(lamp.clone());
+        undo_and_redo(&store, lamp.item_id, change, expect("Lamp", "Garage", 1, true), expect("Lamp", "Garage", 1, false)).await;
+    }
+
+    #[tokio::test]
+    async fn edits_can_be_undone_and_redone() {
+        let store = memory().await;
+        let lamp = add(&store, "Lamp", &[("Room", "Garage")]).await;
+
+        let before = store.update_item(lamp.item_id, &location(&[("Room", "Attic")]), "Desk lamp", "", &[], &[]).await.unwrap();
+
+        let change = Change::Edited(before);
+        undo_and_redo(&store, lamp.item_id, change, expect("Lamp", "Garage", 1, false), expect("Desk lamp", "Attic", 1, false)).await;
+    }
+
+    #[tokio::test]
+    async fn moves_can_be_undone_and_redone() {
+        let store = memory().await;
+        let lamp = add(&store, "Lamp", &[("Room", "Garage"), ("Shelf", "Top")]).await;
+
+        let moved = store.move_items(&[lamp.item_id], &location(&[("Room", "Attic")])).await.unwrap();
+
+        let change = Change::Moved(moved);
+        undo_and_redo(&store, lamp.item_id, change, expect("Lamp", "Garage / Top", 1, false), expect("Lamp", "Attic", 1, false)).await;
+    }
+
+    #[tokio::test]
+    async fn quantity_changes_can_be_undone_and_redone() {
+        let store = memory().await;
+        let lamp = add(&store, "Lamp", &[("Room", "Garage")]).await;
+
+        store.change_quantity(lamp.item_id, 3).await.unwrap();
+
+        let change = Change::QuantityChanged { item_id: lamp.item_id, item_name: lamp.item_name.clone(), by: 3 };
+        undo_and_redo(&store, lamp.item_id, change, expect("Lamp", "Garage", 1, false), expect("Lamp", "Garage", 4, false)).await;
+    }
+
+    #[tokio::test]
+    async fn deletes_and_restores_can_be_undone_and_redone() {
+        let store = memory().await;
+        let lamp = add(&store, "Lamp", &[("Room", "Garage")]).await;
+
+        let deleted = store.delete_item(lamp.item_id).await.unwrap().unwrap();
+        let change = Change::Deleted(deleted);
+        undo_and_redo(&store, lamp.item_id, change, expect("Lamp", "Garage", 1, false), expect("Lamp", "Garage", 1, true)).await;
+
+        store.delete_item(lamp.item_id).await.unwrap();
+        let restored = store.restore_item(lamp.item_id).await.unwrap().unwrap();
+        let change = Change::Restored(restored);
+        undo_and_redo(&store, lamp.item_id, change, expect("Lamp", "Garage", 1, true), expect("Lamp", "Garage", 1, false)).await;
+    }
+
+    async fn count(store: &CatalogStore, sql: &str, item_id: i64) -> i64 {
+        sqlx::query_scalar(sql)
+            .bind(item_id)
+            .fetch_one(&store.pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn trashed_items_are_left_out_until_restored() {
+        let store = memory().await;
+        let lamp = add(&store, "Lamp", &[("Room", "Garage"), ("Shelf", "Top")]).await;
+        add(&store, "Lamp shade", &[("Room", "Garage")]).await;
+
+        store.delete_item(lamp.item_id).await.unwrap();
+
+        let found = store.search("lamp", SearchMode::Substring, &SearchFilter::default()).await.unwrap();
+        assert_eq!(found.iter().map(|item| item.item_name.as_str()).collect::<Vec<_>>(), ["Lamp shade"]);
+        assert_eq!(store.browse(None).await.unwrap().children[0].item_count, 1);
+        let trash = store.trash(None).await.unwrap();
+        assert_eq!(trash.iter().map(|item| item.item_id).collect::<Vec<_>>(), [lamp.item_id]);
+
+        let restored = store.restore_item(lamp.item_id).await.unwrap().unwrap();
+
+        assert_eq!(restored.location_id, lamp.location_id);
+        assert_eq!(restored.location_path, "Garage / Top");
+        assert_eq!(store.browse(None).await.unwrap().children[0].item_count, 2);
+        assert!(store.trash(None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn purging_removes_only_the_purged_items() {
+        let store = memory().await;
+        let lamp = add(&store, "Lamp", &[("Room", "Garage")]).await;
+        let tent = add(&store, "Tent", &[("Room", "Garage")]).await;
+        for item_id in [lamp.item_id, tent.item_id] {
+            sqlx::query("INSERT INTO Photo (item_id, file_name, image, thumbnail) VALUES ($1, 'photo.png', x'00', x'00')")
+                .bind(item_id)
+                .execute(&store.pool)
+                .await
+                .unwrap();
+        }
+
+        // only items in the trash are purged
+        assert_eq!(store.purge(&[lamp.item_id]).await.unwrap(), 0);
+        store.delete_item(lamp.item_id).await.unwrap();
+        assert_eq!(store.purge(&[lamp.item_id]).await.unwrap(), 1);
+
+        assert_eq!(store.load_item(lamp.item_id).await.map(|_| ()), Err(CatalogError::NoSuchItem(lamp.item_id)));
+        assert_eq!(count(&store, "SELECT COUNT(*) FROM Photo WHERE item_id = $1", lamp.item_id).await, 0);
+        // the history outlives the item, ending with it being purged
+        let history = store.history(Some(lamp.item_id)).await.unwrap();
+        assert_eq!(history.first().map(|entry| entry.action), Some(HistoryAction::Purged));
+
+        assert_eq!(store.load_item(tent.item_id).await.unwrap().photos.len(), 1);
+        assert_eq!(count(&store, "SELECT COUNT(*) FROM Photo WHERE item_id = $1", tent.item_id).await, 1);
+    }
+
+    #[tokio::test]
+    async fn only_expired_items_are_purged() {
+        let store = memory().await;
+        let lamp = add(&store, "Lamp", &[("Room", "Garage")]).await;
+        let tent = add(&store, "Tent", &[("Room", "Garage")]).await;
+        store.delete_items(&[lamp.item_id, tent.item_id]).await.unwrap();
+        sqlx::query("UPDATE Item SET deleted_at = datetime('now', '-40 days') WHERE item_id = $1")
+            .bind(lamp.item_id)
+            .execute(&store.pool)
+            .await
+            .unwrap();
+
+        store.purge_expired(30).await.unwrap();
+
+        let trash = store.trash(None).await.unwrap();
+        assert_eq!(trash.iter().map(|item| item.item_id).collect::<Vec<_>>(), [tent.item_id]);
+    }
+
+    #[tokio::test]
+    async fn csv_exports_import_into_another_catalog() {
+        let store = memory().await;
+        add(&store, "Lamp", &[("Room", "Garage"), ("Shelf", "Top")]).await;
+        add(&store, "Tent", &[("Room", "Attic")]).await;
+        // catalogs migrated from version 1 can hold items that were never given a location
+        let stray = add(&store, "Stray sock", &[("Room", "Attic")]).await;
+        sqlx::query("UPDATE Item SET location_id = NULL WHERE item_id = $1")
+            .bind(stray.item_id)
+            .execute(&store.pool)
+            .await
+            .unwrap();
+
+        let exported = store.export().await.unwrap();
+        let contents = export::to_csv(&exported.items, &exported.field_names).unwrap();
+        let file = import::parse(&contents).unwrap();
+        let roles = file.guess_roles(&[]);
+        for (header, role) in file.headers.iter().zip(&roles) {
+            assert!(*role != ColumnRole::Skip || import::is_export_only(header), "{} is skipped", header);
+        }
+        let rows = import::prepare(&file, &roles, &[])
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let copy = memory().await;
+        assert_eq!(copy.import(&rows, "export.csv").await.unwrap(), 3);
+        let mut items = copy.export().await.unwrap().items.into_iter()
+            .map(|item| (item.item.item_name, item.item.location_path))
+            .collect::<Vec<_>>();
+        items.sort();
+        assert_eq!(items, [
+            (String::from("Lamp"), String::from("Garage / Top")),
+            (String::from("Stray sock"), String::from(export::UNSORTED)),
+            (String::from("Tent"), String::from("Attic")),
+        ]);
+    }
+}
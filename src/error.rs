@@ -0,0 +1,215 @@
+//! What can go wrong while working with a catalog.
+
+use std::path::PathBuf;
+
+
+/// Why an operation on a catalog failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CatalogError {
+    /// There is no file at the path a catalog was to be opened from
+    MissingFile(PathBuf),
+    /// The file isn't a catalog, explaining why
+    NotACatalog(String),
+    /// The catalog was created or upgraded by a newer version of Catalog
+    SchemaTooNew {
+        /// The schema version stamped on the catalog
+        version: i64,
+        /// The newest schema version this build understands
+        supported: i64,
+    },
+    /// A change would break one of the rules of the catalog, such as two fields sharing a name
+    ConstraintViolation(String),
+    /// Another connection held on to the catalog for longer than SQLite was willing to wait
+    Locked,
+    /// A file couldn't be read or written
+    Io(String),
+    /// SQLite couldn't carry out a query for any other reason
+    Database(String),
+    /// There is no item with this id
+    NoSuchItem(i64),
+    /// What was asked for can't be done, explaining why
+    Invalid(String),
+}
+
+impl std::fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CatalogError::MissingFile(path) => write!(f, "there is no file at {}", path.display()),
+            CatalogError::SchemaTooNew { version, supported } => write!(
+                f,
+                "the catalog uses schema version {}, but this version of Catalog only understands up to version {}",
+                version,
+                supported
+            ),
+            CatalogError::Locked => write!(f, "the catalog is in use by another program"),
+            CatalogError::NotACatalog(err)
+                | CatalogError::ConstraintViolation(err)
+                | CatalogError::Io(err)
+                | CatalogError::Database(err)
+                | CatalogError::Invalid(err) => write!(f, "{}", err),
+            CatalogError::NoSuchItem(item_id) => write!(f, "there is no item with id {}", item_id),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+/// SQLite's primary result codes that say something more useful than "the query failed".
+/// See <https://www.sqlite.org/rescode.html>, extended codes keep the primary code in their low byte.
+const SQLITE_BUSY: i32 = 5;
+const SQLITE_LOCKED: i32 = 6;
+const SQLITE_READONLY: i32 = 8;
+const SQLITE_IOERR: i32 = 10;
+const SQLITE_FULL: i32 = 13;
+const SQLITE_CANTOPEN: i32 = 14;
+const SQLITE_CONSTRAINT: i32 = 19;
+const SQLITE_NOTADB: i32 = 26;
+
+impl From<sqlx::Error> for CatalogError {
+    fn from(err: sqlx::Error) -> Self {
+        let code = match &err {
+            sqlx::Error::Io(err) => return CatalogError::Io(err.to_string()),
+            sqlx::Error::PoolTimedOut => return CatalogError::Locked,
+            sqlx::Error::Database(err) => err.code()
+                .and_then(|code| code.parse::<i32>().ok())
+                .map(|code| code & 0xff),
+            _ => None,
+        };
+
+        match code {
+            Some(SQLITE_BUSY | SQLITE_LOCKED) => CatalogError::Locked,
+            Some(SQLITE_READONLY | SQLITE_IOERR | SQLITE_FULL | SQLITE_CANTOPEN) => {
+                CatalogError::Io(database_message(&err))
+            }
+            Some(SQLITE_CONSTRAINT) => CatalogError::ConstraintViolation(describe_constraint(&database_message(&err))),
+            Some(SQLITE_NOTADB) => CatalogError::NotACatalog(String::from("the file is not an SQLite database")),
+            _ => CatalogError::Database(err.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for CatalogError {
+    fn from(err: std::io::Error) -> Self {
+        CatalogError::Io(err.to_string())
+    }
+}
+
+/// What SQLite said went wrong, without sqlx's "error returned from database" in front
+fn database_message(err: &sqlx::Error) -> String {
+    match err {
+        sqlx::Error::Database(err) => err.message().to_string(),
+        err => err.to_string(),
+    }
+}
+
+/// Rewords the constraints SQLite reports, e.g. "UNIQUE constraint failed: Field.name",
+/// into what they mean for the catalog
+fn describe_constraint(message: &str) -> String {
+    if let Some(columns) = message.strip_prefix("UNIQUE constraint failed: ")
+        && let Some((table, column)) = columns.split(", ").next().and_then(|column| column.split_once('.')) {
+        return format!("there is already a {} with that {}", table.to_lowercase(), column);
+    }
+    if message.starts_with("FOREIGN KEY constraint failed") {
+        return String::from("something it refers to has been deleted in the meantime");
+    }
+    message.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use sqlx::{sqlite::SqliteConnectOptions, Connection, SqliteConnection};
+
+    use super::{describe_constraint, CatalogError};
+    use crate::CatalogStore;
+
+    /// A path in the temporary directory for a test to put a catalog at, with nothing there yet
+    fn temporary(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("catalog-test-{}-{}.sqlite", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    async fn failure(connection: &mut SqliteConnection, statement: &str) -> CatalogError {
+        let err = sqlx::query(statement)
+            .execute(&mut *connection)
+            .await
+            .unwrap_err();
+        CatalogError::from(err)
+    }
+
+    #[tokio::test]
+    async fn constraints_are_told_apart() {
+        let mut connection = SqliteConnection::connect("sqlite::memory:").await.unwrap();
+        for statement in [
+            "CREATE TABLE Field (field_id INTEGER PRIMARY KEY, name TEXT UNIQUE)",
+            "CREATE TABLE ItemField (field_id INTEGER REFERENCES Field(field_id))",
+            "INSERT INTO Field (field_id, name) VALUES (1, 'Grade')",
+        ] {
+            sqlx::query(statement).execute(&mut connection).await.unwrap();
+        }
+
+        assert_eq!(
+            failure(&mut connection, "INSERT INTO Field (name) VALUES ('Grade')").await,
+            CatalogError::ConstraintViolation(String::from("there is already a field with that name"))
+        );
+        assert_eq!(
+            failure(&mut connection, "INSERT INTO ItemField (field_id) VALUES (7)").await,
+            CatalogError::ConstraintViolation(String::from("something it refers to has been deleted in the meantime"))
+        );
+        assert!(matches!(failure(&mut connection, "SELECT * FROM Nowhere").await, CatalogError::Database(_)));
+    }
+
+    #[test]
+    fn constraints_are_reworded() {
+        assert_eq!(describe_constraint("UNIQUE constraint failed: Tag.name"), "there is already a tag with that name");
+        assert_eq!(describe_constraint("CHECK constraint failed: kind"), "CHECK constraint failed: kind");
+    }
+
+    #[tokio::test]
+    async fn other_files_are_not_catalogs() {
+        let path = temporary("not-a-database");
+        std::fs::write(&path, "Name,Shelf\nLamp,Top\n".repeat(100)).unwrap();
+
+        let result = CatalogStore::open(&path).await.map(|_| ());
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(result, Err(CatalogError::NotACatalog(String::from("the file is not an SQLite database"))));
+    }
+
+    #[tokio::test]
+    async fn missing_files_are_reported() {
+        let path = temporary("missing");
+
+        let result = CatalogStore::open(&path).await.map(|_| ());
+
+        assert_eq!(result, Err(CatalogError::MissingFile(path)));
+    }
+
+    #[tokio::test]
+    async fn catalogs_in_use_are_locked() {
+        let path = temporary("locked");
+        CatalogStore::create(&path).await.unwrap().close().await;
+
+        // another program holds on to the catalog
+        let mut other = SqliteConnection::connect_with(&SqliteConnectOptions::new().filename(&path)).await.unwrap();
+        sqlx::query("BEGIN EXCLUSIVE").execute(&mut other).await.unwrap();
+
+        let options = SqliteConnectOptions::new()
+            .filename(&path)
+            .busy_timeout(Duration::from_millis(100));
+        let result = CatalogStore::connect(options).await.map(|_| ());
+
+        let mut connection = SqliteConnection::connect_with(&SqliteConnectOptions::new().filename(&path).busy_timeout(Duration::ZERO)).await.unwrap();
+        let busy = failure(&mut connection, "SELECT * FROM Item").await;
+
+        other.close().await.unwrap();
+        connection.close().await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(result, Err(CatalogError::Locked));
+        assert_eq!(busy, CatalogError::Locked);
+    }
+}
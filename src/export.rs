@@ -0,0 +1,263 @@
+//! Writing the whole catalog out to CSV or JSON files.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::{CatalogError, ItemInfo, LocationLevel};
+
+
+/// The kinds of file the catalog can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    /// The format going by the extension of `path`, CSV unless it ends in `.json`
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => Format::Json,
+            _ => Format::Csv,
+        }
+    }
+}
+
+/// An item to export along with every level of the location it is stored in
+#[derive(Debug, Clone)]
+pub struct ExportItem {
+    pub item: ItemInfo,
+    /// From the top level location down, empty if the item has no location
+    pub location: Vec<LocationLevel>,
+}
+
+/// Everything needed to write out a catalog, as loaded by `CatalogStore::export`
+#[derive(Debug, Clone)]
+pub struct Export {
+    pub items: Vec<ExportItem>,
+    /// The names of every custom field, in the order they were added
+    pub field_names: Vec<String>,
+}
+
+/// How an item is written out as JSON, by exports and by the command line's `--json`
+#[derive(Serialize)]
+pub struct JsonItem<'a> {
+    id: i64,
+    name: &'a str,
+    notes: &'a str,
+    quantity: i64,
+    /// The names of the locations from the root down, e.g. "Garage / Rack 2 / Shelf 3 / Bin B"
+    location_path: &'a str,
+    /// Left out when the levels weren't loaded, or the item has no location
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    location: Vec<JsonLevel<'a>>,
+    tags: &'a [String],
+    fields: BTreeMap<&'a str, &'a str>,
+    created_at: Option<&'a str>,
+    updated_at: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct JsonLevel<'a> {
+    kind: &'a str,
+    name: &'a str,
+    description: &'a str,
+}
+
+impl<'a> JsonItem<'a> {
+    /// `location` holds each level of the item's location from the top down, or nothing to only give its path
+    pub fn new(item: &'a ItemInfo, location: &'a [LocationLevel]) -> JsonItem<'a> {
+        JsonItem {
+            id: item.item_id,
+            name: &item.item_name,
+            notes: &item.item_notes,
+            quantity: item.quantity,
+            location_path: &item.location_path,
+            location: location.iter()
+                .map(|level| JsonLevel {
+                    kind: &level.kind,
+                    name: &level.name,
+                    description: &level.description,
+                })
+                .collect(),
+            tags: &item.tags,
+            fields: item.fields.iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect(),
+            created_at: item.created_at.as_deref(),
+            updated_at: item.updated_at.as_deref(),
+        }
+    }
+}
+
+/// Writes `items` as a JSON array with an object for each item
+pub fn to_json(items: &[ExportItem]) -> Result<Vec<u8>, String> {
+    let items = items.iter()
+        .map(|ExportItem { item, location }| JsonItem::new(item, location))
+        .collect::<Vec<_>>();
+
+    serde_json::to_vec_pretty(&items)
+        .map_err(|err| err.to_string())
+}
+
+/// The location CSV exports put items that have no location in, since importing needs one for every item
+pub const UNSORTED: &str = "Unsorted";
+
+/// Writes `items` as a CSV file that the Import screen can read back in.
+/// Each level of the location gets a "Location 1", "Location 2", … column for its name
+/// followed by a "Location 1 kind" column for its kind, since the kinds at the same depth can differ,
+/// and each of `field_names` gets a column of its own.
+/// Items without a location are written as being in a top level location named [`UNSORTED`].
+pub fn to_csv(items: &[ExportItem], field_names: &[String]) -> Result<Vec<u8>, String> {
+    let unsorted = [LocationLevel {
+        kind: String::from("Location"),
+        name: String::from(UNSORTED),
+        description: String::new(),
+    }];
+    let depth = items.iter()
+        .map(|item| item.location.len().max(1))
+        .max()
+        .unwrap_or_default();
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    let mut headers = vec![String::from("Path")];
+    for level in 1..=depth {
+        headers.push(format!("Location {}", level));
+        headers.push(format!("Location {} kind", level));
+    }
+    headers.extend(["Name", "Notes", "Quantity", "Tags"].map(String::from));
+    headers.extend(field_names.iter().cloned());
+    headers.extend(["Added", "Changed"].map(String::from));
+    writer.write_record(&headers)
+        .map_err(|err| err.to_string())?;
+
+    for ExportItem { item, location } in items {
+        let mut record = vec![item.location_path.clone()];
+        let location = if location.is_empty() { &unsorted[..] } else { location };
+        for level in 0..depth {
+            let level = location.get(level);
+            record.push(level.map(|level| level.name.clone()).unwrap_or_default());
+            record.push(level.map(|level| level.kind.clone()).unwrap_or_default());
+        }
+        record.extend([
+            item.item_name.clone(),
+            item.item_notes.clone(),
+            item.quantity.to_string(),
+            item.tags.join(", "),
+        ]);
+        record.extend(field_names.iter().map(|name| {
+            item.fields.iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value.clone())
+                .unwrap_or_default()
+        }));
+        record.extend([
+            item.created_at.clone().unwrap_or_default(),
+            item.updated_at.clone().unwrap_or_default(),
+        ]);
+        writer.write_record(&record)
+            .map_err(|err| err.to_string())?;
+    }
+
+    writer.into_inner()
+        .map_err(|err| err.to_string())
+}
+
+/// Writes `export` to `path` in the format its extension asks for
+pub async fn save(path: &Path, export: &Export) -> Result<(), CatalogError> {
+    let file_name = path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let contents = match Format::from_path(path) {
+        Format::Csv => to_csv(&export.items, &export.field_names),
+        Format::Json => to_json(&export.items),
+    };
+
+    let contents = contents
+        .map_err(|err| CatalogError::Invalid(format!("{} couldn't be written: {}", file_name, err)))?;
+
+    tokio::fs::write(path, contents).await
+        .map_err(|err| CatalogError::Io(format!("{} couldn't be written: {}", file_name, err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_csv, ExportItem};
+    use crate::{import, FieldInfo, FieldKind, ItemInfo, LocationLevel};
+
+    fn level(kind: &str, name: &str) -> LocationLevel {
+        LocationLevel {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            description: String::new(),
+        }
+    }
+
+    fn item(item_id: i64, name: &str, location: Vec<LocationLevel>, fields: &[(&str, &str)]) -> ExportItem {
+        let location_path = location.iter()
+            .map(|level| level.name.as_str())
+            .collect::<Vec<_>>()
+            .join(" / ");
+        ExportItem {
+            item: ItemInfo {
+                item_id,
+                location_id: Some(item_id),
+                location_path,
+                item_name: name.to_string(),
+                item_notes: String::from("Bought in 2019, with receipt"),
+                quantity: item_id,
+                tags: vec![String::from("Camping"), String::from("Winter")],
+                fields: fields.iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+                thumbnail: None,
+                created_at: Some(String::from("2024-01-02 03:04:05")),
+                updated_at: None,
+                deleted_at: None,
+            },
+            location,
+        }
+    }
+
+    #[test]
+    fn csv_exports_can_be_imported_again() {
+        let items = vec![
+            item(1, "Sleeping bag", vec![level("Room", "Garage"), level("Closet", "Hall closet"), level("Tote", "Blue tote")], &[("Size", "2")]),
+            item(2, "Tent", vec![level("Rack", "Rack 1"), level("Shelf", "Top")], &[]),
+            item(3, "Stove", vec![level("Location", "Shed")], &[("Size", "10")]),
+        ];
+        let fields = vec![FieldInfo {
+            field_id: 4,
+            name: String::from("Size"),
+            kind: FieldKind::Number,
+            choices: Vec::new(),
+        }];
+
+        let contents = to_csv(&items, &[String::from("Size")]).unwrap();
+        let file = import::parse(&contents).unwrap();
+        let roles = file.guess_roles(&fields);
+        assert_eq!(import::check_roles(&roles), None);
+        let rows = import::prepare(&file, &roles, &fields);
+
+        assert_eq!(rows.len(), items.len());
+        for (row, ExportItem { item, location }) in rows.into_iter().zip(&items) {
+            let row = row.unwrap();
+            let levels = |location: &[LocationLevel]| location.iter()
+                .map(|level| (level.kind.clone(), level.name.clone()))
+                .collect::<Vec<_>>();
+            assert_eq!(levels(&row.item.location), levels(location));
+            assert_eq!(row.item.name, item.item_name);
+            assert_eq!(row.item.notes, item.item_notes);
+            assert_eq!(row.item.quantity, item.quantity);
+            assert_eq!(row.item.tags, item.tags);
+            let fields = item.fields.iter()
+                .map(|(_, value)| (4, value.clone()))
+                .collect::<Vec<_>>();
+            assert_eq!(row.item.fields, fields);
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Typo tolerant matching for item search.
+
+
+/// The smallest number of single character insertions, deletions and substitutions needed to turn
+/// `query` into some substring of `text`, so "acton comcs" is 2 away from "9.8 Action Comics #1".
+/// The comparison ignores case.
+pub fn substring_distance(query: &str, text: &str) -> usize {
+    let query = query.to_lowercase().chars().collect::<Vec<_>>();
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+
+    // previous[j] is the distance between the query so far and the best substring ending at text[j - 1].
+    // A match may start anywhere in the text, so the first row costs nothing.
+    let mut previous = vec![0; text.len() + 1];
+
+    for (i, query_char) in query.iter().enumerate() {
+        let mut current = vec![i + 1; text.len() + 1];
+        for (j, text_char) in text.iter().enumerate() {
+            let substitution = previous[j] + usize::from(query_char != text_char);
+            current[j + 1] = substitution
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous.into_iter().min().unwrap_or(0)
+}
+
+/// How many typos a query may contain and still match.
+/// Short queries have to match exactly since a single typo would match nearly everything.
+pub fn tolerance(query: &str) -> usize {
+    query.chars().count() / 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{substring_distance, tolerance};
+
+    #[test]
+    fn exact_substrings_cost_nothing() {
+        assert_eq!(substring_distance("action", "9.8 Action Comics #1"), 0);
+        assert_eq!(substring_distance("9.8 Action Comics #1", "9.8 Action Comics #1"), 0);
+    }
+
+    #[test]
+    fn typos_cost_one_each() {
+        assert_eq!(substring_distance("acton", "9.8 Action Comics #1"), 1);
+        assert_eq!(substring_distance("actoin", "9.8 Action Comics #1"), 2);
+        assert_eq!(substring_distance("acton comcs", "9.8 Action Comics #1"), 2);
+    }
+
+    #[test]
+    fn empty_queries_match_anything() {
+        assert_eq!(substring_distance("", "Lamp"), 0);
+        assert_eq!(substring_distance("", ""), 0);
+    }
+
+    #[test]
+    fn queries_longer_than_the_name_cost_the_extra_characters() {
+        assert_eq!(substring_distance("lamp shade", "Lamp"), 6);
+        assert_eq!(substring_distance("lamp", ""), 4);
+    }
+
+    #[test]
+    fn non_ascii_names_are_compared_by_character() {
+        assert_eq!(substring_distance("café", "Café Table"), 0);
+        assert_eq!(substring_distance("ÄPFEL", "Grüne Äpfel"), 0);
+        assert_eq!(substring_distance("cafe", "Café Table"), 1);
+        assert_eq!(substring_distance("日本", "日本酒"), 0);
+    }
+
+    #[test]
+    fn longer_queries_tolerate_more_typos() {
+        assert_eq!(tolerance("box"), 0);
+        assert_eq!(tolerance("lamp"), 1);
+        assert_eq!(tolerance("café"), 1);
+        assert_eq!(tolerance("acton comcs"), 2);
+    }
+}
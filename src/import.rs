@@ -0,0 +1,363 @@
+//! Reading items from CSV files for the Import screen.
+
+use std::path::Path;
+
+use crate::{CatalogError, FieldInfo, LocationLevel, NewItem};
+
+
+/// What a column of a CSV file holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColumnRole {
+    Skip,
+    /// One level of the location, with the header of the column as the kind of location, e.g. "Shelf".
+    /// The location columns go from the outermost level inward in the order they appear in the file.
+    Location,
+    /// The kind of a level of the location, e.g. "Shelf", for files where it changes from item to item.
+    /// The first of these columns is the kind of the first location column, the second of the second, and so on.
+    LocationKind,
+    Name,
+    Notes,
+    Quantity,
+    /// Tags separated by commas
+    Tags,
+    /// The value of the custom field with this name
+    Field(String),
+}
+
+impl std::fmt::Display for ColumnRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColumnRole::Skip => write!(f, "Skip"),
+            ColumnRole::Location => write!(f, "Location level"),
+            ColumnRole::LocationKind => write!(f, "Kind of location level"),
+            ColumnRole::Name => write!(f, "Name"),
+            ColumnRole::Notes => write!(f, "Notes"),
+            ColumnRole::Quantity => write!(f, "Quantity"),
+            ColumnRole::Tags => write!(f, "Tags"),
+            ColumnRole::Field(name) => write!(f, "Field: {}", name),
+        }
+    }
+}
+
+impl ColumnRole {
+    /// Every role a column can be given in a catalog with `fields`
+    pub fn all(fields: &[FieldInfo]) -> Vec<ColumnRole> {
+        let mut roles = vec![
+            ColumnRole::Skip,
+            ColumnRole::Location,
+            ColumnRole::LocationKind,
+            ColumnRole::Name,
+            ColumnRole::Notes,
+            ColumnRole::Quantity,
+            ColumnRole::Tags,
+        ];
+        roles.extend(fields.iter().map(|field| ColumnRole::Field(field.name.clone())));
+        roles
+    }
+
+    /// A role for a column going by its header, e.g. "Shelf" is a location and "Qty" a quantity.
+    /// The "Location 1" and "Location 1 kind" columns of exported files are recognised too.
+    fn guess(header: &str, fields: &[FieldInfo]) -> ColumnRole {
+        let header = header.trim().to_lowercase();
+        if let Some(field) = fields.iter().find(|field| field.name.to_lowercase() == header) {
+            return ColumnRole::Field(field.name.clone());
+        }
+        if let Some(level) = header.strip_suffix(" kind")
+            && is_numbered_location(level) {
+            return ColumnRole::LocationKind;
+        }
+        if is_numbered_location(&header) {
+            return ColumnRole::Location;
+        }
+
+        match header.as_str() {
+            "name" | "item" | "item name" | "title" => ColumnRole::Name,
+            "notes" | "note" | "description" => ColumnRole::Notes,
+            "quantity" | "qty" | "count" => ColumnRole::Quantity,
+            "tags" | "tag" => ColumnRole::Tags,
+            "location" | "room" | "rack" | "shelf" | "basket" | "bin" | "box" | "drawer" | "cabinet" => ColumnRole::Location,
+            _ => ColumnRole::Skip,
+        }
+    }
+}
+
+/// Whether `header` names a column that exports write for people to read but importing has no use for,
+/// e.g. the whole location path or when the item was added
+pub fn is_export_only(header: &str) -> bool {
+    matches!(header.trim().to_lowercase().as_str(), "path" | "added" | "changed")
+}
+
+/// Whether `header` is "location" followed by a number, as exported files name their location columns
+fn is_numbered_location(header: &str) -> bool {
+    header.strip_prefix("location ")
+        .is_some_and(|number| !number.is_empty() && number.bytes().all(|digit| digit.is_ascii_digit()))
+}
+
+/// The kind of location a location column holds when nothing says otherwise, going by its header,
+/// or nothing for headers like "Location 2" that don't name a kind
+fn header_kind(header: &str) -> &str {
+    if is_numbered_location(&header.to_lowercase()) {
+        ""
+    } else {
+        header
+    }
+}
+
+/// The contents of a CSV file, where the first line holds the headers
+#[derive(Debug, Clone, Default)]
+pub struct CsvFile {
+    pub headers: Vec<String>,
+    /// The line each record starts on along with its values, which may be fewer or more than the headers
+    pub records: Vec<(u64, Vec<String>)>,
+}
+
+impl CsvFile {
+    /// A role for each column going by its header
+    pub fn guess_roles(&self, fields: &[FieldInfo]) -> Vec<ColumnRole> {
+        self.headers.iter()
+            .map(|header| ColumnRole::guess(header, fields))
+            .collect()
+    }
+}
+
+/// An item read from a record of a CSV file, ready to be imported
+#[derive(Debug, Clone)]
+pub struct ImportRow {
+    /// The line of the file the item was read from
+    pub line: u64,
+    pub item: NewItem,
+}
+
+/// Parses `contents` as a CSV file with a header line
+pub fn parse(contents: &[u8]) -> Result<CsvFile, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(contents);
+
+    let headers = reader.headers()
+        .map_err(|err| err.to_string())?
+        .iter()
+        .map(String::from)
+        .collect();
+
+    let mut records = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|err| err.to_string())?;
+        let line = record.position().map(|position| position.line()).unwrap_or_default();
+        records.push((line, record.iter().map(String::from).collect()));
+    }
+
+    Ok(CsvFile { headers, records })
+}
+
+/// Reads and parses the CSV file at `path`
+pub async fn read(path: &Path) -> Result<CsvFile, CatalogError> {
+    let file_name = path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let contents = tokio::fs::read(path).await
+        .map_err(|err| CatalogError::Io(format!("{} couldn't be read: {}", file_name, err)))?;
+
+    parse(&contents)
+        .map_err(|err| CatalogError::Invalid(format!("{} isn't a CSV file: {}", file_name, err)))
+}
+
+/// Why `roles` can't be used to import anything, if they can't
+pub fn check_roles(roles: &[ColumnRole]) -> Option<String> {
+    if !roles.contains(&ColumnRole::Name) {
+        return Some(String::from("Pick the column that holds the names of the items"));
+    }
+    if !roles.contains(&ColumnRole::Location) {
+        return Some(String::from("Pick at least one column for where the items are kept"));
+    }
+    None
+}
+
+/// Reads an item out of each record of `file` using the role of each of its columns,
+/// or explains what is wrong with the record
+pub fn prepare(file: &CsvFile, roles: &[ColumnRole], fields: &[FieldInfo]) -> Vec<Result<ImportRow, String>> {
+    file.records.iter()
+        .map(|(line, values)| {
+            prepare_record(&file.headers, roles, fields, values)
+                .map(|item| ImportRow { line: *line, item })
+                .map_err(|err| format!("Line {}: {}", line, err))
+        })
+        .collect()
+}
+
+fn prepare_record(
+    headers: &[String],
+    roles: &[ColumnRole],
+    fields: &[FieldInfo],
+    values: &[String],
+) -> Result<NewItem, String> {
+    let mut item = NewItem {
+        location: Vec::new(),
+        name: String::new(),
+        notes: String::new(),
+        quantity: 1,
+        tags: Vec::new(),
+        fields: Vec::new(),
+    };
+    // the kind and name of each location level, and the kinds given by any kind columns,
+    // which are only put together once every column has been read
+    let mut levels: Vec<(&str, &str)> = Vec::new();
+    let mut kinds: Vec<&str> = Vec::new();
+
+    for (index, role) in roles.iter().enumerate() {
+        let value = values.get(index).map(String::as_str).unwrap_or_default();
+        let header = headers.get(index).map(String::as_str).unwrap_or_default();
+
+        match role {
+            ColumnRole::Skip => {}
+            ColumnRole::Location => levels.push((header_kind(header), value)),
+            ColumnRole::LocationKind => kinds.push(value),
+            ColumnRole::Name => item.name = value.to_string(),
+            ColumnRole::Notes => item.notes = value.to_string(),
+            ColumnRole::Quantity => {
+                if !value.is_empty() {
+                    item.quantity = match value.parse::<i64>() {
+                        Ok(quantity) if quantity >= 1 => quantity,
+                        _ => return Err(format!("the quantity has to be a whole number of at least 1, not {}", value)),
+                    };
+                }
+            }
+            ColumnRole::Tags => {
+                for tag in value.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+                    // tags ignore case, so "comic" is the same tag as "Comic"
+                    if !item.tags.iter().any(|known| known.eq_ignore_ascii_case(tag)) {
+                        item.tags.push(tag.to_string());
+                    }
+                }
+            }
+            ColumnRole::Field(name) => {
+                let Some(field) = fields.iter().find(|field| field.name == *name) else {
+                    return Err(format!("there is no field named {} any more", name));
+                };
+                if value.is_empty() {
+                    continue;
+                }
+                field.check(value)?;
+                item.fields.retain(|(field_id, _)| *field_id != field.field_id);
+                item.fields.push((field.field_id, value.to_string()));
+            }
+        }
+    }
+
+    // the kind of the first location level left empty, since no level can come after it
+    let mut missing_level: Option<&str> = None;
+    for (index, (kind, name)) in levels.into_iter().enumerate() {
+        let kind = kinds.get(index)
+            .copied()
+            .filter(|kind| !kind.is_empty())
+            .unwrap_or(kind);
+        // what to call the level in messages when no kind was given
+        let called = if kind.is_empty() { "Location" } else { kind };
+        if name.is_empty() {
+            missing_level.get_or_insert(called);
+            continue;
+        }
+        if let Some(missing) = missing_level {
+            return Err(format!("there is a {} but no {}", called, missing));
+        }
+        item.location.push(LocationLevel {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            description: String::new(),
+        });
+    }
+
+    if item.name.is_empty() {
+        return Err(String::from("the item has no name"));
+    }
+    if item.location.is_empty() {
+        return Err(format!("{} has no location", item.name));
+    }
+
+    Ok(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, prepare, ColumnRole};
+    use crate::{FieldInfo, FieldKind};
+
+    fn size_field() -> FieldInfo {
+        FieldInfo {
+            field_id: 7,
+            name: String::from("Size"),
+            kind: FieldKind::Number,
+            choices: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn headers_are_guessed() {
+        let file = parse(b"Item Name, QTY ,Room,Shelf,Tag,Description,size,Colour\n").unwrap();
+
+        assert_eq!(file.guess_roles(&[size_field()]), vec![
+            ColumnRole::Name,
+            ColumnRole::Quantity,
+            ColumnRole::Location,
+            ColumnRole::Location,
+            ColumnRole::Tags,
+            ColumnRole::Notes,
+            ColumnRole::Field(String::from("Size")),
+            ColumnRole::Skip,
+        ]);
+
+        let file = parse(b"Location 1,Location 1 kind,Location 12,Location kind,Location 1 name\n").unwrap();
+        assert_eq!(file.guess_roles(&[]), vec![
+            ColumnRole::Location,
+            ColumnRole::LocationKind,
+            ColumnRole::Location,
+            ColumnRole::Skip,
+            ColumnRole::Skip,
+        ]);
+    }
+
+    #[test]
+    fn quoted_values_keep_their_commas_and_newlines() {
+        let contents = "Name,Notes,Shelf\n\"Cable, long\",\"first line\nsecond line\",Top\nLamp,,Bottom\n";
+        let file = parse(contents.as_bytes()).unwrap();
+        let rows = prepare(&file, &file.guess_roles(&[]), &[]);
+
+        let first = rows[0].as_ref().unwrap();
+        assert_eq!(first.line, 2);
+        assert_eq!(first.item.name, "Cable, long");
+        assert_eq!(first.item.notes, "first line\nsecond line");
+        // the quoted newline makes the next record start a line later
+        assert_eq!(rows[1].as_ref().unwrap().line, 4);
+    }
+
+    #[test]
+    fn bad_quantities_are_refused() {
+        let file = parse(b"Name,Quantity,Shelf\nA,zero,Top\nB,0,Top\nC,-1,Top\nD,1.5,Top\nE,,Top\nF,3,Top\n").unwrap();
+        let rows = prepare(&file, &file.guess_roles(&[]), &[]);
+
+        assert_eq!(rows[0].as_ref().unwrap_err(), "Line 2: the quantity has to be a whole number of at least 1, not zero");
+        assert!(rows[1].is_err());
+        assert!(rows[2].is_err());
+        assert!(rows[3].is_err());
+        assert_eq!(rows[4].as_ref().unwrap().item.quantity, 1);
+        assert_eq!(rows[5].as_ref().unwrap().item.quantity, 3);
+    }
+
+    #[test]
+    fn empty_location_levels() {
+        let file = parse(b"Name,Rack,Shelf,Basket\nA,Left,,Red\nB,Left,Top,\nC,,,\n").unwrap();
+        let rows = prepare(&file, &file.guess_roles(&[]), &[]);
+
+        assert_eq!(rows[0].as_ref().unwrap_err(), "Line 2: there is a Basket but no Shelf");
+
+        let location = &rows[1].as_ref().unwrap().item.location;
+        let levels = location.iter()
+            .map(|level| (level.kind.as_str(), level.name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(levels, vec![("Rack", "Left"), ("Shelf", "Top")]);
+
+        assert_eq!(rows[2].as_ref().unwrap_err(), "Line 4: C has no location");
+    }
+}
@@ -0,0 +1,444 @@
+//! Reading and changing catalogs, the SQLite files the Catalog app keeps track of things in.
+//!
+//! Open a catalog with `CatalogStore::open`, then use its methods to add, find, move and delete items:
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), catalog::CatalogError> {
+//! use catalog::{CatalogStore, SearchFilter, SearchMode};
+//!
+//! let store = CatalogStore::open("home.sqlite").await?;
+//! for item in store.search("lamp", SearchMode::Substring, &SearchFilter::default()).await? {
+//!     println!("{} is in {}", item.item_name, item.location_path);
+//! }
+//! store.close().await;
+//! # Ok(())
+//! # }
+//! ```
+
+mod database;
+mod error;
+mod fuzzy;
+mod migrations;
+pub mod export;
+pub mod import;
+pub mod thumbnail;
+
+pub use database::{CatalogStore, HIGHLIGHT_END, HIGHLIGHT_START};
+pub use error::CatalogError;
+
+
+#[derive(Debug, Clone)]
+pub struct ItemInfo {
+    pub item_id: i64,
+    pub location_id: Option<i64>,
+    /// Names of the locations from the root down, e.g. "Garage / Rack 2 / Shelf 3 / Bin B"
+    pub location_path: String,
+    pub item_name: String,
+    pub item_notes: String,
+    /// How many of this item are stored together, at least 1
+    pub quantity: i64,
+    /// Sorted ignoring case
+    pub tags: Vec<String>,
+    /// The name and value of each custom field the item has a value for
+    pub fields: Vec<(String, String)>,
+    /// The thumbnail of the first photo attached to the item, if it has any
+    pub thumbnail: Option<Thumbnail>,
+    /// When the item was added, in UTC, unknown for items from before this was recorded
+    pub created_at: Option<String>,
+    /// When the item was last changed, in UTC
+    pub updated_at: Option<String>,
+    /// When the item was put in the trash, in UTC, `None` unless it is in the trash
+    pub deleted_at: Option<String>,
+}
+
+/// A photo attached to an item
+#[derive(Debug, Clone)]
+pub struct PhotoInfo {
+    pub photo_id: i64,
+    /// The name of the file the photo was attached from
+    pub file_name: String,
+    pub thumbnail: Thumbnail,
+}
+
+/// A small preview of a photo, see `thumbnail::make`
+#[derive(Clone)]
+pub struct Thumbnail {
+    /// The photo this is a preview of
+    pub photo_id: i64,
+    /// The preview encoded as a PNG
+    pub png: Vec<u8>,
+}
+
+impl std::fmt::Debug for Thumbnail {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Thumbnail")
+            .field("photo_id", &self.photo_id)
+            .field("png", &format_args!("{} bytes", self.png.len()))
+            .finish()
+    }
+}
+
+/// One step of a location path, e.g. the kind "Shelf" with the name "Top shelf".
+/// When an item is added or moved, levels are matched to existing locations by name alone, ignoring case.
+/// A non-empty kind or description given for an existing location replaces the stored one,
+/// while an empty one leaves it as it is, and a new location without a kind gets the kind "Location".
+#[derive(Debug, Clone, Default)]
+pub struct LocationLevel {
+    pub kind: String,
+    pub name: String,
+    pub description: String,
+}
+
+/// The kind of value a custom field holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FieldKind {
+    #[default]
+    Text,
+    Number,
+    /// Written as YYYY-MM-DD
+    Date,
+    /// One of a fixed list of values
+    Choice,
+}
+
+impl FieldKind {
+    pub const ALL: [FieldKind; 4] = [FieldKind::Text, FieldKind::Number, FieldKind::Date, FieldKind::Choice];
+
+    /// The name stored in the `kind` column of the `Field` table
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FieldKind::Text => "text",
+            FieldKind::Number => "number",
+            FieldKind::Date => "date",
+            FieldKind::Choice => "choice",
+        }
+    }
+
+    pub fn from_name(name: &str) -> FieldKind {
+        FieldKind::ALL.into_iter()
+            .find(|kind| kind.as_str() == name)
+            .unwrap_or_default()
+    }
+}
+
+impl std::fmt::Display for FieldKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldKind::Text => write!(f, "Text"),
+            FieldKind::Number => write!(f, "Number"),
+            FieldKind::Date => write!(f, "Date"),
+            FieldKind::Choice => write!(f, "Choice"),
+        }
+    }
+}
+
+/// What was done to an item in an entry of its history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HistoryAction {
+    Added,
+    /// Any change made on the Edit screen or to its quantity
+    #[default]
+    Edited,
+    Moved,
+    /// Put in the trash
+    Deleted,
+    /// Taken back out of the trash
+    Restored,
+    /// Emptied from the trash for good
+    Purged,
+}
+
+impl HistoryAction {
+    pub const ALL: [HistoryAction; 6] = [
+        HistoryAction::Added,
+        HistoryAction::Edited,
+        HistoryAction::Moved,
+        HistoryAction::Deleted,
+        HistoryAction::Restored,
+        HistoryAction::Purged,
+    ];
+
+    /// The name stored in the `action` column of the `History` table
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryAction::Added => "added",
+            HistoryAction::Edited => "edited",
+            HistoryAction::Moved => "moved",
+            HistoryAction::Deleted => "deleted",
+            HistoryAction::Restored => "restored",
+            HistoryAction::Purged => "purged",
+        }
+    }
+
+    pub fn from_name(name: &str) -> HistoryAction {
+        HistoryAction::ALL.into_iter()
+            .find(|action| action.as_str() == name)
+            .unwrap_or_default()
+    }
+}
+
+impl std::fmt::Display for HistoryAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistoryAction::Added => write!(f, "Added"),
+            HistoryAction::Edited => write!(f, "Edited"),
+            HistoryAction::Moved => write!(f, "Moved"),
+            HistoryAction::Deleted => write!(f, "Deleted"),
+            HistoryAction::Restored => write!(f, "Restored"),
+            HistoryAction::Purged => write!(f, "Purged"),
+        }
+    }
+}
+
+/// One change recorded in the history of the catalog
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub item_id: i64,
+    /// The name of the item once the change was made, kept so deleted items can still be named
+    pub item_name: String,
+    pub action: HistoryAction,
+    /// What changed, e.g. "from Garage / Shelf 1 to Attic / Box 3"
+    pub details: String,
+    /// The account that made the change
+    pub actor: String,
+    /// When the change was made, in UTC
+    pub happened_at: String,
+}
+
+/// An item about to be added to the catalog
+#[derive(Debug, Clone)]
+pub struct NewItem {
+    pub location: Vec<LocationLevel>,
+    pub name: String,
+    pub notes: String,
+    pub quantity: i64,
+    pub tags: Vec<String>,
+    /// The id and value of each custom field given
+    pub fields: Vec<(i64, String)>,
+}
+
+/// What can be changed about an item on the Edit screen, enough to put it back the way it was
+#[derive(Debug, Clone)]
+pub struct ItemSnapshot {
+    pub item: ItemInfo,
+    /// The id and value of each custom field the item has a value for
+    pub fields: Vec<(i64, String)>,
+}
+
+/// A change made to the catalog, with what is needed to undo it
+#[derive(Debug, Clone)]
+pub enum Change {
+    Added(ItemInfo),
+    /// The item as it was before the edit
+    Edited(ItemSnapshot),
+    /// The moved items with the location each was moved from
+    Moved(Vec<(i64, Option<i64>)>),
+    QuantityChanged {
+        item_id: i64,
+        item_name: String,
+        by: i64,
+    },
+    /// Put in the trash
+    Deleted(ItemInfo),
+    /// Taken back out of the trash
+    Restored(ItemInfo),
+}
+
+impl Change {
+    /// What the change did, e.g. "deleting Lamp"
+    pub fn describe(&self) -> String {
+        match self {
+            Change::Added(item) => format!("adding {}", item.item_name),
+            Change::Edited(snapshot) => format!("editing {}", snapshot.item.item_name),
+            Change::Moved(items) => format!("moving {} item(s)", items.len()),
+            Change::QuantityChanged { item_name, .. } => format!("changing the quantity of {}", item_name),
+            Change::Deleted(item) => format!("deleting {}", item.item_name),
+            Change::Restored(item) => format!("restoring {}", item.item_name),
+        }
+    }
+}
+
+/// A custom field declared for the catalog, such as a grade for comics or a platform for games
+#[derive(Debug, Clone)]
+pub struct FieldInfo {
+    pub field_id: i64,
+    pub name: String,
+    pub kind: FieldKind,
+    /// The values a `FieldKind::Choice` field can take, empty for every other kind
+    pub choices: Vec<String>,
+}
+
+impl FieldInfo {
+    /// Whether `value` can be stored in this field, explaining what it needs if not
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        let valid = match self.kind {
+            FieldKind::Text => true,
+            FieldKind::Number => value.parse::<f64>().is_ok_and(f64::is_finite),
+            FieldKind::Date => is_date(value),
+            FieldKind::Choice => self.choices.iter().any(|choice| choice == value),
+        };
+        if valid {
+            return Ok(());
+        }
+
+        Err(match self.kind {
+            FieldKind::Date => format!("{} needs a date written as YYYY-MM-DD", self.name),
+            FieldKind::Choice => format!("{} needs one of {}", self.name, self.choices.join(", ")),
+            _ => format!("{} needs a number", self.name),
+        })
+    }
+}
+
+/// Whether `value` is a date written as YYYY-MM-DD
+fn is_date(value: &str) -> bool {
+    let parts = value.split('-').collect::<Vec<_>>();
+    let [year, month, day] = parts.as_slice() else {
+        return false;
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return false;
+    }
+
+    match (year.parse::<u32>(), month.parse::<u32>(), day.parse::<u32>()) {
+        (Ok(_), Ok(month), Ok(day)) => (1..=12).contains(&month) && (1..=31).contains(&day),
+        _ => false,
+    }
+}
+
+/// How a custom field is compared by a `FieldFilter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FieldOp {
+    /// Equal to the value, ignoring case
+    #[default]
+    Is,
+    /// Contains the value anywhere, ignoring case
+    Contains,
+    AtLeast,
+    AtMost,
+}
+
+impl FieldOp {
+    pub const ALL: [FieldOp; 4] = [FieldOp::Is, FieldOp::Contains, FieldOp::AtLeast, FieldOp::AtMost];
+}
+
+impl std::fmt::Display for FieldOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldOp::Is => write!(f, "is"),
+            FieldOp::Contains => write!(f, "contains"),
+            FieldOp::AtLeast => write!(f, "at least"),
+            FieldOp::AtMost => write!(f, "at most"),
+        }
+    }
+}
+
+/// Keeps the search results whose custom field called `name` compares to `value` using `op`.
+/// Numbers are compared as numbers, everything else as text.
+#[derive(Debug, Clone, Default)]
+pub struct FieldFilter {
+    pub name: String,
+    pub kind: FieldKind,
+    pub op: FieldOp,
+    pub value: String,
+}
+
+/// What every search result has to have besides matching the query
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilter {
+    pub tags: Vec<String>,
+    pub fields: Vec<FieldFilter>,
+}
+
+/// An item found by a search, along with the parts of it that matched
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub item: ItemInfo,
+    /// The item name with the matched terms wrapped in `HIGHLIGHT_START` and `HIGHLIGHT_END`
+    pub name: Option<String>,
+    /// A short excerpt of the notes around the matched terms, marked up the same way as `name`
+    pub notes: Option<String>,
+}
+
+impl From<ItemInfo> for SearchHit {
+    fn from(item: ItemInfo) -> Self {
+        SearchHit {
+            item,
+            name: None,
+            notes: None,
+        }
+    }
+}
+
+/// How the Search screen matches item names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    /// The name contains the query anywhere, ignoring case
+    #[default]
+    Substring,
+    /// The name starts with the query, ignoring case
+    Prefix,
+    /// The name contains something close to the query, allowing for typos
+    Fuzzy,
+    /// The name or notes contain every word of the query, using the full text index
+    FullText,
+}
+
+impl SearchMode {
+    pub const ALL: [SearchMode; 4] = [SearchMode::Substring, SearchMode::Prefix, SearchMode::Fuzzy, SearchMode::FullText];
+}
+
+impl std::fmt::Display for SearchMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchMode::Substring => write!(f, "Contains"),
+            SearchMode::Prefix => write!(f, "Starts with"),
+            SearchMode::Fuzzy => write!(f, "Fuzzy"),
+            SearchMode::FullText => write!(f, "Names and notes"),
+        }
+    }
+}
+
+/// A location that is already stored in the catalog
+#[derive(Debug, Clone)]
+pub struct LocationInfo {
+    pub location_id: i64,
+    pub kind: String,
+    pub name: String,
+    pub description: String,
+}
+
+/// A location shown on the Browse screen
+#[derive(Debug, Clone)]
+pub struct LocationSummary {
+    pub location: LocationInfo,
+    /// How many items are stored in this location, including inside of the locations within it
+    pub item_count: i64,
+}
+
+/// What adding an item did
+#[derive(Debug, Clone)]
+pub enum AddOutcome {
+    Added(ItemInfo),
+    /// Nothing was added because this item with the same name is already stored in the same location
+    IdenticalFound(ItemInfo),
+}
+
+/// Everything about a single item, for editing it or showing it in full
+#[derive(Debug, Clone)]
+pub struct ItemDetails {
+    pub item: ItemInfo,
+    /// Each level of the item's location from the top down
+    pub location: Vec<LocationLevel>,
+    pub photos: Vec<PhotoInfo>,
+}
+
+/// What is inside of a location
+#[derive(Debug, Clone)]
+pub struct LocationContents {
+    /// The locations leading to this one from the top level, ending with this one
+    pub trail: Vec<LocationInfo>,
+    /// The locations directly inside of this one
+    pub children: Vec<LocationSummary>,
+    /// The items stored directly in this location
+    pub items: Vec<ItemInfo>,
+}
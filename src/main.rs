@@ -1,28 +1,189 @@
-mod database;
+mod cli;
+mod tasks;
 
 use iced::{
-    futures::{SinkExt, Stream}, stream, widget::{button, column, horizontal_space, row, text, text_input, scrollable, Button, Column, Row}, window, Element, Subscription, Task
+    futures::{SinkExt, Stream}, stream, font, keyboard, widget::{button, checkbox, column, horizontal_space, image, pick_list, rich_text, row, span, text, text_input, scrollable, Button, Column, Row}, window, Alignment, Element, Font, Subscription, Task
 };
 use serde::{Deserialize, Serialize};
-use sqlx::{Sqlite, Pool};
+use std::collections::HashMap;
+
+use catalog::{export, import, thumbnail, CatalogError, CatalogStore, Change, FieldFilter, FieldInfo, FieldKind, FieldOp, HistoryAction, HistoryEntry, ItemInfo, LocationInfo, LocationLevel, LocationSummary, PhotoInfo, SearchFilter, SearchHit, SearchMode, Thumbnail};
 
 
 fn main() -> iced::Result {
+    let cli = <cli::Cli as clap::Parser>::parse();
+    if cli.command.is_some() {
+        std::process::exit(cli::run(cli));
+    }
 
     iced::application(Catalog::title, Catalog::update, Catalog::view)
         .subscription(Catalog::subscriptions)
         .run()
 }
 
+/// A location being entered level by level on the Add and Edit screens
+#[derive(Debug)]
+pub struct LocationEditor {
+    levels: Vec<LocationLevel>,
+    /// The existing locations that can be picked for each of `levels`
+    choices: Vec<Vec<LocationInfo>>,
+    error: String,
+}
+
+impl LocationEditor {
+    fn new() -> Self {
+        Self::with_levels(vec![LocationLevel::default()])
+    }
+
+    fn with_levels(levels: Vec<LocationLevel>) -> Self {
+        let choices = vec![Vec::new(); levels.len()];
+        LocationEditor {
+            levels,
+            choices,
+            error: String::new(),
+        }
+    }
+
+    /// The name entered at each level, used to look up the choices for the level below it
+    fn path(&self) -> Vec<String> {
+        self.levels.iter()
+            .map(|level| level.name.trim().to_string())
+            .collect()
+    }
+
+    /// The entered location with surrounding whitespace removed,
+    /// or `None` after setting `error` if any level is incomplete
+    fn validate(&mut self) -> Option<Vec<LocationLevel>> {
+        if self.levels.iter().any(|level| level.kind.trim().is_empty() || level.name.trim().is_empty()) {
+            self.error = String::from("Every location level needs a kind and a name");
+            return None;
+        }
+
+        let levels = self.levels.iter()
+            .map(|level| LocationLevel {
+                kind: level.kind.trim().to_string(),
+                name: level.name.trim().to_string(),
+                description: level.description.trim().to_string(),
+            })
+            .collect();
+        Some(levels)
+    }
+}
+
+/// Tags being put on an item on the Add and Edit screens, or required of the results on the Search screen
+#[derive(Debug, Default)]
+pub struct TagEditor {
+    tags: Vec<String>,
+    /// A new tag being typed in
+    input: String,
+    /// Every tag already used in the catalog, so they can be picked instead of typed again
+    known: Vec<String>,
+}
+
+impl TagEditor {
+    fn with_tags(tags: Vec<String>) -> Self {
+        TagEditor {
+            tags,
+            ..TagEditor::default()
+        }
+    }
+
+    /// Adds `tag` unless it is blank or already there, ignoring case
+    fn add(&mut self, tag: &str) {
+        let tag = tag.trim();
+        if tag.is_empty() || self.tags.iter().any(|existing| existing.to_lowercase() == tag.to_lowercase()) {
+            return;
+        }
+        self.tags.push(tag.to_string());
+    }
+
+    /// The known tags that haven't been added yet
+    fn unused(&self) -> Vec<String> {
+        self.known.iter()
+            .filter(|tag| !self.tags.iter().any(|existing| existing.to_lowercase() == tag.to_lowercase()))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Values for the catalog's custom fields being entered on the Add and Edit screens
+#[derive(Debug, Default)]
+pub struct FieldEditor {
+    fields: Vec<FieldInfo>,
+    /// The value entered for each field by name, a field without one is left unset
+    values: Vec<(String, String)>,
+    error: String,
+}
+
+impl FieldEditor {
+    fn with_values(values: Vec<(String, String)>) -> Self {
+        FieldEditor {
+            values,
+            ..FieldEditor::default()
+        }
+    }
+
+    fn value(&self, name: &str) -> &str {
+        self.values.iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+            .unwrap_or_default()
+    }
+
+    fn set(&mut self, name: String, value: String) {
+        self.values.retain(|(field, _)| *field != name);
+        self.values.push((name, value));
+        self.error = String::new();
+    }
+
+    /// The entered values by field id with surrounding whitespace removed and empty ones left out,
+    /// or `None` after setting `error` if a value doesn't suit its field
+    fn validate(&mut self) -> Option<Vec<(i64, String)>> {
+        let mut values = Vec::new();
+        for field in self.fields.iter() {
+            let value = self.value(&field.name).trim().to_string();
+            if value.is_empty() {
+                continue;
+            }
+
+            if let Err(error) = field.check(&value) {
+                self.error = error;
+                return None;
+            }
+
+            values.push((field.field_id, value));
+        }
+        Some(values)
+    }
+}
+
+/// A search run from the Search screen, kept so that it can be run again after an undo or redo
 #[derive(Debug, Clone)]
-pub struct ItemInfo {
-    rack_number: String,
-    shelf_number: String,
-    basket_number: String,
-    item_name: String,
-    item_notes: String,
+pub enum LastSearch {
+    Query {
+        query: String,
+        mode: SearchMode,
+        filter: SearchFilter,
+    },
+    Location {
+        location: Vec<String>,
+        name: String,
+        filter: SearchFilter,
+    },
 }
 
+impl LastSearch {
+    fn run(self, database: CatalogStore) -> Task<Message> {
+        match self {
+            LastSearch::Query { query, mode, filter } => {
+                Task::perform(tasks::search(database, query, mode, filter), |x| x)
+            }
+            LastSearch::Location { location, name, filter } => {
+                Task::perform(tasks::search_by_location(database, location, name, filter), |x| x)
+            }
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -31,6 +192,7 @@ pub enum Message {
     DumpedConfig,
     WelcomePressed,
     SearchPressed,
+    BrowsePressed,
     AddPressed,
     DeletePressed,
     InitializationFailed(String),
@@ -40,66 +202,279 @@ pub enum Message {
     InitializeOpenFilePicker,
     CreateDatabase(String),
     OpenDatabase(String),
-    OpenDatabaseSuccess(Pool<Sqlite>),
-    CreateDatabaseSuccess(Pool<Sqlite>),
-    CreateDatabaseFailure(String),
-    DatabaseTransactionSuccess(Pool<Sqlite>),
-    DatabaseTransactionFailure(Pool<Sqlite>, String),
-    AddRackUpdate(String),
-    AddShelfUpdate(String),
-    AddBasketUpdate(String),
-    AddItemUpdate(String),
-    AddNotesUpdate(String),
+    OpenDatabaseSuccess(CatalogStore),
+    /// The path that couldn't be opened and why
+    OpenDatabaseFailure(String, CatalogError),
+    CreateDatabaseSuccess(CatalogStore),
+    /// The path where a catalog couldn't be created and why
+    CreateDatabaseFailure(String, CatalogError),
+    DatabaseTransactionSuccess,
+    DatabaseTransactionFailure(CatalogError),
+    LocationKindUpdate(usize, String),
+    LocationNameUpdate(usize, String),
+    LocationDescriptionUpdate(usize, String),
+    LocationPicked(usize, String),
+    LocationChoicesLoaded(Vec<Vec<LocationInfo>>),
+    LocationPush,
+    LocationPop,
+    ItemNameUpdate(String),
+    ItemNotesUpdate(String),
+    TagInputUpdate(String),
+    TagSubmit,
+    TagPicked(String),
+    TagRemove(String),
+    TagsLoaded(Vec<String>),
+    FieldValueUpdate(String, String),
+    FieldsPressed,
+    HistoryPressed,
+    ImportPressed,
+    ImportPickFile,
+    ImportFileLoaded(String, Result<import::CsvFile, CatalogError>),
+    ImportRoleChanged(usize, import::ColumnRole),
+    ImportConfirm,
+    ItemsImported(usize),
+    ExportPressed,
+    ExportFilePicked(std::path::PathBuf),
+    ExportLoaded(std::path::PathBuf, export::Export),
+    /// What was exported, or why it couldn't be
+    CatalogExported(Result<String, CatalogError>),
+    TrashPressed,
+    TrashLoaded(Vec<ItemInfo>),
+    TrashAutoPurgeToggled(bool),
+    TrashDaysUpdate(String),
+    RestoreItem(i64),
+    /// `None` if the item wasn't in the trash
+    ItemRestored(Option<Change>),
+    PurgeSelect(Vec<i64>),
+    PurgeCancel,
+    PurgeConfirm,
+    TrashPurged(u64),
+    Undo,
+    Redo,
+    /// The change that redoes what was undone
+    Undone(Change),
+    /// The change that undoes what was redone
+    Redone(Change),
+    /// The change that couldn't be undone, to be put back, and why
+    UndoFailed(Change, CatalogError),
+    /// The change that couldn't be redone, to be put back, and why
+    RedoFailed(Change, CatalogError),
+    HistoryLoaded(Option<i64>, Vec<HistoryEntry>),
+    FieldsLoaded(Vec<FieldInfo>),
+    FieldsChanged,
+    FieldNameUpdate(String),
+    FieldKindChanged(FieldKind),
+    FieldChoicesUpdate(String),
+    FieldCreate,
+    FieldDelete(i64),
+    FieldFilterPush,
+    FieldFilterRemove(usize),
+    FieldFilterPicked(usize, String),
+    FieldFilterOpChanged(usize, FieldOp),
+    FieldFilterValueUpdate(usize, String),
     AddItem,
-    DatabaseSearchSuccess(Pool<Sqlite>, Vec<ItemInfo>),
-    DatabaseSearchFailure(Pool<Sqlite>),
+    ItemAdded(Change),
+    IdenticalItemFound(ItemInfo),
+    AddToIdentical,
+    AddSeparately,
+    QuantityChange(i64, i64),
+    QuantityChanged(i64, i64, Change),
+    EditPressed(i64),
+    DetailPressed(i64),
+    /// The item to show on the Detail screen, if it is still showing that item
+    DetailLoaded(ItemInfo, Vec<LocationLevel>, Vec<PhotoInfo>),
+    /// The item to open the Edit screen with
+    EditLoaded(ItemInfo, Vec<LocationLevel>, Vec<PhotoInfo>),
+    EditSave,
+    AttachPhotos,
+    PhotosPicked(Vec<std::path::PathBuf>),
+    PhotosChanged(i64),
+    PhotosLoaded(i64, Vec<PhotoInfo>),
+    PhotoRemove(i64),
+    ItemUpdated(Change),
+    DatabaseSearchSuccess(Vec<ItemInfo>),
+    DatabaseTextSearchSuccess(Vec<SearchHit>),
+    DatabaseSearchFailure,
     SearchQueryUpdate(String),
+    SearchModeChanged(SearchMode),
     SearchQuery,
+    SearchToggleSelected(i64, bool),
+    SearchLocationUpdate(usize, String),
+    SearchLocationPush,
+    SearchLocationPop,
+    SearchLocationQuery,
+    RebuildSearchIndex,
+    MoveItem(i64),
+    MoveSelected,
+    MoveConfirm,
+    ItemsMoved(Change),
     DeleteQueryUpdate(String),
     DeleteQuery,
+    DeleteSelect(i64),
+    DeleteCancel,
+    DeleteConfirm,
+    /// `None` if the item was already gone
+    ItemDeleted(Option<Change>),
+    BrowseTo(Option<i64>),
+    BrowseLoaded(Vec<LocationInfo>, Vec<LocationSummary>, Vec<ItemInfo>),
     None,
 }
 
+// there is only ever one screen, so the size of its biggest variant doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum Screen {
     Starting,
     InitializeEmpty(String),
-    InitializeChoice(String),
+    /// The path being entered and the last error from opening or creating a database
+    InitializeChoice(String, String),
     InitializeError(String),
     Welcome,
     Add {
-        rack_number: String,
-        rack_error: String,
-        shelf_number: String,
-        shelf_error: String,
-        basket_number: String,
-        basket_error: String,
+        location: LocationEditor,
+        item_name: String,
+        item_notes: String,
+        tags: TagEditor,
+        fields: FieldEditor,
+        /// An item with the same name already stored in the location, waiting for the user to
+        /// decide between adding to its quantity or adding a separate item
+        identical: Option<ItemInfo>,
+        status: String,
+    },
+    Edit {
+        item_id: i64,
+        location: LocationEditor,
         item_name: String,
         item_notes: String,
+        tags: TagEditor,
+        fields: FieldEditor,
+        photos: Vec<PhotoInfo>,
+        thumbnails: Thumbnails,
+        status: String,
+    },
+    Detail {
+        item_id: i64,
+        /// `None` while the item is loading and once it has been deleted
+        item: Option<ItemInfo>,
+        /// Every level of the item's location, from the outermost inward
+        location: Vec<LocationLevel>,
+        photos: Vec<PhotoInfo>,
+        thumbnails: Thumbnails,
+        /// The changes made to the item, newest first
+        history: Vec<HistoryEntry>,
+        /// Whether the user has asked to delete the item and not yet confirmed
+        confirming_delete: bool,
+        status: String,
     },
     Search {
         query: String,
-        result: Vec<ItemInfo>
+        mode: SearchMode,
+        result: Vec<SearchHit>,
+        thumbnails: Thumbnails,
+        /// The search that found `result`, `None` until one has been run
+        last_search: Option<LastSearch>,
+        /// The ids of the results that have been ticked for moving
+        selected: Vec<i64>,
+        /// A location name to match at each level, an empty one matches any location
+        location_filter: Vec<String>,
+        /// The tags every result must have
+        tags: TagEditor,
+        /// Every custom field, to pick from when filtering
+        fields: Vec<FieldInfo>,
+        field_filters: Vec<FieldFilter>,
+    },
+    Browse {
+        /// The locations leading to the one being browsed, empty at the top level
+        trail: Vec<LocationInfo>,
+        children: Vec<LocationSummary>,
+        items: Vec<ItemInfo>,
+        thumbnails: Thumbnails,
+    },
+    Move {
+        items: Vec<ItemInfo>,
+        location: LocationEditor,
+        status: String,
+    },
+    Fields {
+        fields: Vec<FieldInfo>,
+        /// The new field being declared
+        name: String,
+        kind: FieldKind,
+        /// The values a new choice field can take, separated by commas
+        choices: String,
+        error: String,
+    },
+    Import {
+        /// The name of the file being imported, empty until one is picked
+        file_name: String,
+        file: import::CsvFile,
+        /// What each column of `file` holds
+        roles: Vec<import::ColumnRole>,
+        /// Every custom field, to map columns to
+        fields: Vec<FieldInfo>,
+        /// The item read from each record of `file`, or what is wrong with the record
+        rows: Vec<Result<import::ImportRow, String>>,
+        status: String,
+    },
+    Trash {
+        /// The items in the trash, the most recently deleted first
+        items: Vec<ItemInfo>,
+        /// The number of days entered for purging the trash automatically
+        days: String,
+        /// The items that will be purged once the user confirms, empty unless they have asked to
+        confirming: Vec<i64>,
+        status: String,
+    },
+    History {
+        /// The latest changes made to any item, newest first
+        entries: Vec<HistoryEntry>,
+        status: String,
     },
     Delete {
         item_name: String,
+        /// The items whose names contain `item_name`
+        matches: Vec<ItemInfo>,
+        /// The item that will be removed once the user confirms
+        confirming: Option<ItemInfo>,
         result: String,
     }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Config {
-    database_paths: Vec<String>
+    database_paths: Vec<String>,
+    /// How many days deleted items stay in the trash before they are purged, `None` to keep them until it is emptied
+    #[serde(default)]
+    trash_days: Option<u32>,
 }
 
 
 pub struct Catalog {
     screen: Screen,
     config: Config,
-    current_database: Option<Pool<Sqlite>>,
+    current_database: Option<CatalogStore>,
+    /// The changes that can be undone, the latest last
+    undo: Vec<Change>,
+    /// The changes that were undone and can be redone, the latest last
+    redo: Vec<Change>,
+    /// Whether an undo or redo is running. Another one waits until it is done,
+    /// since they could finish out of order and mix up `undo` and `redo`.
+    reverting: bool,
+    /// What the last undo, redo or export did, or why it couldn't be done
+    controls_status: String,
     //toasts: Vec<Toast>,
 }
 
+/// How many changes can be undone
+const UNDO_LIMIT: usize = 100;
+/// How many lines of a CSV file are shown under each of its columns on the Import screen
+const IMPORT_SAMPLE_LINES: usize = 3;
+/// How many of the lines of a CSV file that can be imported are previewed on the Import screen,
+/// the lines with problems are always all listed
+const IMPORT_PREVIEW_LINES: usize = 200;
+/// How many days items stay in the trash when automatic purging is first turned on
+const DEFAULT_TRASH_DAYS: u32 = 30;
+
 impl Catalog {
     pub fn title(&self) -> String {
         String::from("Catalog")
@@ -108,8 +483,8 @@ impl Catalog {
     pub fn update(&mut self, event: Message) -> Task<Message> {
         match event {
             Message::Shutdown => {
-                if let Some(pool) = self.current_database.take() {
-                    Task::perform(database::close_database(pool), |x| x)
+                if let Some(database) = self.current_database.take() {
+                    Task::perform(tasks::close_database(database), |x| x)
                 } else {
                     Task::perform(Self::dump_config(self.config.clone()), |x| x)
                 }
@@ -130,23 +505,354 @@ impl Catalog {
             }
             Message::AddPressed => {
                 self.screen = Screen::Add {
-                    rack_number: String::new(),
-                    rack_error: String::new(),
-                    shelf_number: String::new(),
-                    shelf_error: String::new(),
-                    basket_number: String::new(),
-                    basket_error: String::new(),
+                    location: LocationEditor::new(),
                     item_name: String::new(),
                     item_notes: String::new(),
+                    tags: TagEditor::default(),
+                    fields: FieldEditor::default(),
+                    identical: None,
+                    status: String::new(),
                 };
-                Task::none()
+                self.load_tags()
             }
             Message::SearchPressed => {
-                self.screen = Screen::Search { result: Vec::new(), query: String::new() };
+                self.screen = Screen::Search {
+                    result: Vec::new(),
+                    thumbnails: Thumbnails::new(),
+                    last_search: None,
+                    query: String::new(),
+                    mode: SearchMode::default(),
+                    selected: Vec::new(),
+                    location_filter: vec![String::new()],
+                    tags: TagEditor::default(),
+                    fields: Vec::new(),
+                    field_filters: Vec::new(),
+                };
+                self.load_tags()
+            }
+            Message::BrowsePressed => {
+                self.screen = Screen::Browse {
+                    trail: Vec::new(),
+                    children: Vec::new(),
+                    items: Vec::new(),
+                    thumbnails: Thumbnails::new(),
+                };
+                self.update(Message::BrowseTo(None))
+            }
+            Message::FieldsPressed => {
+                self.screen = Screen::Fields {
+                    fields: Vec::new(),
+                    name: String::new(),
+                    kind: FieldKind::default(),
+                    choices: String::new(),
+                    error: String::new(),
+                };
+                self.load_fields()
+            }
+            Message::HistoryPressed => {
+                if let Some(database) = self.current_database.clone() {
+                    self.screen = Screen::History {
+                        entries: Vec::new(),
+                        status: String::from("Loading..."),
+                    };
+                    Task::perform(tasks::history(database, None), |x| x)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::HistoryLoaded(item_id, loaded) => {
+                match &mut self.screen {
+                    Screen::History { entries, status } if item_id.is_none() => {
+                        *entries = loaded;
+                        *status = String::new();
+                    }
+                    Screen::Detail { item_id: shown, history, .. } if item_id == Some(*shown) => {
+                        *history = loaded;
+                    }
+                    _ => {}
+                }
+                Task::none()
+            }
+            Message::ImportPressed => {
+                self.screen = Screen::Import {
+                    file_name: String::new(),
+                    file: import::CsvFile::default(),
+                    roles: Vec::new(),
+                    fields: Vec::new(),
+                    rows: Vec::new(),
+                    status: String::new(),
+                };
+                self.load_fields()
+            }
+            Message::ImportPickFile => {
+                use rfd::AsyncFileDialog;
+
+                let future = async {
+                    let file = AsyncFileDialog::new()
+                        .add_filter("csv", &["csv"])
+                        .pick_file()
+                        .await;
+
+                    match file {
+                        None => Message::None,
+                        Some(file) => tasks::load_import(file.path().to_path_buf()).await,
+                    }
+                };
+
+                Task::perform(future, |x| x)
+            }
+            Message::ImportFileLoaded(loaded_name, result) => {
+                if let Screen::Import { file_name, file, roles, fields, rows, status } = &mut self.screen {
+                    match result {
+                        Err(err) => *status = explain(&err),
+                        Ok(loaded) => {
+                            *roles = loaded.guess_roles(fields);
+                            *rows = import::prepare(&loaded, roles, fields);
+                            *file = loaded;
+                            *file_name = loaded_name;
+                            *status = String::new();
+                        }
+                    }
+                }
+                Task::none()
+            }
+            Message::ImportRoleChanged(index, role) => {
+                if let Screen::Import { file, roles, fields, rows, status, .. } = &mut self.screen
+                    && let Some(column) = roles.get_mut(index) {
+                    *column = role;
+                    *rows = import::prepare(file, roles, fields);
+                    *status = String::new();
+                }
+                Task::none()
+            }
+            Message::ImportConfirm => {
+                let Screen::Import { file_name, roles, rows, status, .. } = &mut self.screen else {
+                    return Task::none();
+                };
+                if import::check_roles(roles).is_some() || rows.is_empty() || rows.iter().any(Result::is_err) {
+                    return Task::none();
+                }
+
+                if let Some(database) = self.current_database.clone() {
+                    *status = String::from("Importing...");
+                    let rows = rows.iter()
+                        .flatten()
+                        .cloned()
+                        .collect();
+                    Task::perform(tasks::import(database, rows, file_name.clone()), |x| x)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::ItemsImported(imported) => {
+                if let Screen::Import { file_name, file, roles, rows, status, .. } = &mut self.screen {
+                    *status = format!("Imported {} item(s) from {}", imported, file_name);
+                    *file_name = String::new();
+                    *file = import::CsvFile::default();
+                    roles.clear();
+                    rows.clear();
+                }
+                Task::none()
+            }
+            Message::ExportPressed => {
+                use rfd::AsyncFileDialog;
+
+                let future = async {
+                    let file = AsyncFileDialog::new()
+                        .add_filter("CSV", &["csv"])
+                        .add_filter("JSON", &["json"])
+                        .set_file_name("catalog.csv")
+                        .save_file()
+                        .await;
+
+                    match file {
+                        None => Message::None,
+                        Some(file) => Message::ExportFilePicked(file.path().to_path_buf()),
+                    }
+                };
+
+                Task::perform(future, |x| x)
+            }
+            Message::ExportFilePicked(path) => {
+                if let Some(database) = self.current_database.clone() {
+                    self.controls_status = String::new();
+                    Task::perform(tasks::export(database, path), |x| x)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::ExportLoaded(path, export) => {
+                Task::perform(tasks::save_export(path, export), |x| x)
+            }
+            Message::CatalogExported(result) => {
+                self.controls_status = result.unwrap_or_else(|err| explain(&err));
+                Task::none()
+            }
+            Message::TrashPressed => {
+                if let Some(database) = self.current_database.clone() {
+                    let days = self.config.trash_days.unwrap_or(DEFAULT_TRASH_DAYS);
+                    self.screen = Screen::Trash {
+                        items: Vec::new(),
+                        days: days.to_string(),
+                        confirming: Vec::new(),
+                        status: String::from("Loading..."),
+                    };
+                    Task::perform(tasks::trash(database, self.config.trash_days), |x| x)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::TrashLoaded(loaded) => {
+                if let Screen::Trash { items, confirming, status, .. } = &mut self.screen {
+                    *items = loaded;
+                    confirming.clear();
+                    *status = String::new();
+                }
+                Task::none()
+            }
+            Message::TrashAutoPurgeToggled(enabled) => {
+                if let Screen::Trash { days, .. } = &self.screen {
+                    self.config.trash_days = if enabled {
+                        Some(days.trim().parse().unwrap_or(DEFAULT_TRASH_DAYS))
+                    } else {
+                        None
+                    };
+                }
+                Task::none()
+            }
+            Message::TrashDaysUpdate(input) => {
+                if let Screen::Trash { days, status, .. } = &mut self.screen {
+                    match input.trim().parse::<u32>() {
+                        Ok(parsed) if parsed > 0 => {
+                            if self.config.trash_days.is_some() {
+                                self.config.trash_days = Some(parsed);
+                            }
+                            *status = String::new();
+                        }
+                        _ => *status = String::from("The number of days must be a whole number above 0"),
+                    }
+                    *days = input;
+                }
+                Task::none()
+            }
+            Message::RestoreItem(item_id) => {
+                if let Some(database) = self.current_database.clone() {
+                    Task::perform(tasks::restore(database, item_id), |x| x)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::ItemRestored(restored) => {
+                let Some(change) = restored else {
+                    return Task::none();
+                };
+                if let Screen::Trash { items, status, .. } = &mut self.screen
+                    && let Change::Restored(restored) = &change {
+                    items.retain(|item| item.item_id != restored.item_id);
+                    *status = format!("Restored {} to {}", restored.item_name, restored.location_path);
+                }
+                self.record_change(change);
+                if let Screen::Detail { .. } = self.screen {
+                    return self.refresh();
+                }
+                Task::none()
+            }
+            Message::PurgeSelect(item_ids) => {
+                if let Screen::Trash { confirming, .. } = &mut self.screen {
+                    *confirming = item_ids;
+                }
+                Task::none()
+            }
+            Message::PurgeCancel => {
+                if let Screen::Trash { confirming, .. } = &mut self.screen {
+                    confirming.clear();
+                }
+                Task::none()
+            }
+            Message::PurgeConfirm => {
+                let Screen::Trash { confirming, .. } = &mut self.screen else {
+                    return Task::none();
+                };
+                if confirming.is_empty() {
+                    return Task::none();
+                }
+                if let Some(database) = self.current_database.clone() {
+                    Task::perform(tasks::purge(database, confirming.clone()), |x| x)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::TrashPurged(purged) => {
+                if let Screen::Trash { items, confirming, status, .. } = &mut self.screen {
+                    items.retain(|item| !confirming.contains(&item.item_id));
+                    confirming.clear();
+                    *status = format!("Deleted {} item(s) for good", purged);
+                }
+                Task::none()
+            }
+            Message::Undo => {
+                if self.reverting {
+                    return Task::none();
+                }
+                if let Some(change) = self.undo.pop() {
+                    let Some(database) = self.current_database.clone() else {
+                        self.undo.push(change);
+                        return Task::none();
+                    };
+                    self.reverting = true;
+                    self.controls_status = format!("Undid {}", change.describe());
+                    Task::perform(tasks::undo(database, change), |x| x)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::Redo => {
+                if self.reverting {
+                    return Task::none();
+                }
+                if let Some(change) = self.redo.pop() {
+                    let Some(database) = self.current_database.clone() else {
+                        self.redo.push(change);
+                        return Task::none();
+                    };
+                    self.reverting = true;
+                    // what is being redone is only known once it has been, see Redone
+                    self.controls_status = String::new();
+                    Task::perform(tasks::redo(database, change), |x| x)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::Undone(change) => {
+                self.reverting = false;
+                self.redo.push(change);
+                self.refresh()
+            }
+            Message::Redone(change) => {
+                self.reverting = false;
+                self.controls_status = format!("Redid {}", change.describe());
+                self.undo.push(change);
+                self.refresh()
+            }
+            Message::UndoFailed(change, err) => {
+                self.reverting = false;
+                self.undo.push(change);
+                self.controls_status = format!("Couldn't do that because {}", explain(&err));
+                Task::none()
+            }
+            Message::RedoFailed(change, err) => {
+                self.reverting = false;
+                self.redo.push(change);
+                self.controls_status = format!("Couldn't do that because {}", explain(&err));
                 Task::none()
             }
             Message::DeletePressed => {
-                self.screen = Screen::Delete { item_name: String::new(), result: String::new() };
+                self.screen = Screen::Delete {
+                    item_name: String::new(),
+                    matches: Vec::new(),
+                    confirming: None,
+                    result: String::new(),
+                };
                 Task::none()
             }
             Message::InitializationFailed(msg) => {
@@ -161,33 +867,30 @@ impl Catalog {
 
                     return Task::none();
                 }
-                self.screen = Screen::InitializeChoice(String::new());
+                self.screen = Screen::InitializeChoice(String::new(), String::new());
                 Task::none()
             }
             Message::InitializeInputChanged(input) => {
-                match &mut self.screen {
-                    Screen::InitializeEmpty(msg) => {
-                        *msg = input
-                    }
-                    _ => {},
+                if let Screen::InitializeEmpty(msg) = &mut self.screen {
+                    *msg = input
                 }
                 Task::none()
             },
             Message::InitializeSubmit => {
                 match &mut self.screen {
                     Screen::InitializeEmpty(path) => {
-                        if path.len() == 0 {
+                        if path.is_empty() {
                             /*self.toasts.push(
                                 Toast::new("Submit", String::from("please provide a path to a .sqlite file"), Status::Error)
                             );*/
                             return Task::none();
                         }
                         self.config.database_paths.push(path.clone());
-                        self.screen = Screen::InitializeChoice(String::new());
+                        self.screen = Screen::InitializeChoice(String::new(), String::new());
                         Task::none()
                     }
-                    Screen::InitializeChoice(path) => {
-                        if path.len() == 0 {
+                    Screen::InitializeChoice(path, _) => {
+                        if path.is_empty() {
                             /*self.toasts.push(
                                 Toast::new("Submit", String::from("please provide a path to a .sqlite file"), Status::Error)
                             );*/
@@ -240,173 +943,576 @@ impl Catalog {
                 Task::perform(future, |x| x)
             }
             Message::OpenDatabase(path) => {
-                Task::perform(database::open_database(path), |x| x)
+                Task::perform(tasks::open_database(path), |x| x)
             }
             Message::CreateDatabase(path) => {
-                Task::perform(database::create_database(path), |x| x)
+                Task::perform(tasks::create_database(path), |x| x)
             }
             Message::OpenDatabaseSuccess(database) => {
-                self.current_database = Some(database);
+                self.screen = Screen::Welcome;
+                self.current_database = Some(database.clone());
+                match self.config.trash_days {
+                    Some(days) => Task::perform(tasks::purge_expired(database, days), |x| x),
+                    None => Task::none(),
+                }
+            }
+            Message::OpenDatabaseFailure(path, err) => {
+                if let Screen::InitializeChoice(_, error) = &mut self.screen {
+                    *error = format!("Could not open {}: {}", path, explain(&err));
+                }
                 Task::none()
             }
             Message::CreateDatabaseSuccess(database) => {
                 self.screen = Screen::Welcome;
-                Task::perform(database::initialize_database(database), |x| x)
-            }
-            Message::CreateDatabaseFailure(msg) => {
-                println!("{}", msg);
-                //self.toasts.push(Toast::new("Database Failure", msg, Status::Error));
+                self.current_database = Some(database);
                 Task::none()
             }
-            Message::DatabaseTransactionSuccess(pool) => {
-                self.current_database = Some(pool);
+            Message::CreateDatabaseFailure(path, err) => {
+                if let Screen::InitializeChoice(_, error) = &mut self.screen {
+                    *error = format!("Could not create {}: {}", path, explain(&err));
+                }
+                //self.toasts.push(Toast::new("Database Failure", msg, Status::Error));
                 Task::none()
             }
-            Message::DatabaseTransactionFailure(pool, msg) => {
-                self.current_database = Some(pool);
-                println!("{}", msg);
-                //self.toasts.push(Toast::new("Database Failure", msg, Status::Error));
+            Message::DatabaseTransactionSuccess => {
                 Task::none()
             }
-            Message::AddRackUpdate(rack_number) => {
+            Message::DatabaseTransactionFailure(err) => {
+                let msg = format!("Couldn't do that because {}", explain(&err));
+                // show it on the screen that asked, or under the controls on screens without a status of their own
                 match &mut self.screen {
-                    Screen::Add { rack_number: rack, rack_error, .. } => {
-                        *rack = rack_number;
-                        *rack_error = String::new();
+                    Screen::Add { status, .. }
+                        | Screen::Edit { status, .. }
+                        | Screen::Detail { status, .. }
+                        | Screen::Move { status, .. }
+                        | Screen::History { status, .. }
+                        | Screen::Trash { status, .. }
+                        | Screen::Import { status, .. } => {
+                        *status = msg;
                     }
-                    _ => {}
+                    Screen::Fields { error, .. } => *error = msg,
+                    Screen::Delete { result, .. } => *result = msg,
+                    _ => self.controls_status = msg,
                 }
                 Task::none()
             }
-            Message::AddShelfUpdate(shelf_number) => {
-                match &mut self.screen {
-                    Screen::Add { shelf_number: shelf, shelf_error, .. } => {
-                        *shelf = shelf_number;
-                        *shelf_error = String::new();
+            Message::LocationKindUpdate(index, kind) => {
+                if let Some(location) = self.location_editor() {
+                    if let Some(level) = location.levels.get_mut(index) {
+                        level.kind = kind;
                     }
-                    _ => {}
+                    location.error = String::new();
                 }
                 Task::none()
             }
-            Message::AddBasketUpdate(basket_number) => {
-                match &mut self.screen {
-                    Screen::Add { basket_number: basket, basket_error, .. } => {
-                        *basket = basket_number;
-                        *basket_error = String::new();
+            Message::LocationNameUpdate(index, name) => {
+                if let Some(location) = self.location_editor() {
+                    if let Some(level) = location.levels.get_mut(index) {
+                        level.name = name;
                     }
-                    _ => {}
+                    location.error = String::new();
                 }
-                Task::none()
+                self.load_location_choices()
             }
-            Message::AddItemUpdate(item_name) => {
-                match &mut self.screen {
-                    Screen::Add { item_name: item, .. } => {
-                        *item = item_name;
-                    }
-                    _ => {}
+            Message::LocationDescriptionUpdate(index, description) => {
+                if let Some(location) = self.location_editor()
+                    && let Some(level) = location.levels.get_mut(index) {
+                    level.description = description;
                 }
                 Task::none()
             }
-            Message::AddNotesUpdate(item_notes) => {
-                match &mut self.screen {
-                    Screen::Add { item_notes: item, .. } => {
-                        *item = item_notes;
+            Message::LocationPicked(index, name) => {
+                if let Some(location) = self.location_editor() {
+                    let picked = location.choices.get(index)
+                        .and_then(|choices| choices.iter().find(|choice| choice.name == name));
+                    if let Some(picked) = picked.cloned()
+                        && let Some(level) = location.levels.get_mut(index) {
+                        level.kind = picked.kind;
+                        level.name = picked.name;
+                        level.description = picked.description;
                     }
-                    _ => {}
+                    location.error = String::new();
                 }
-                Task::none()
+                self.load_location_choices()
             }
-            Message::AddItem => {
-                match &mut self.screen {
-                    Screen::Add {
-                        rack_number,
-                        rack_error,
-                        shelf_number,
-                        shelf_error,
-                        basket_number,
-                        basket_error,
-                        item_name,
-                        item_notes,
-                    } => {
-                        if let Some(database) = self.current_database.take() {
-
-                            let mut errored = false;
-                            let rack = match rack_number.parse::<i64>() {
-                                Ok(rack) => rack,
-                                Err(_) => {
-                                    errored = true;
-                                    *rack_error = String::from("Expected a number");
-                                    0
-                                }
-                            };
-
-                            let shelf = match shelf_number.parse::<u64>() {
-                                Ok(shelf) => shelf,
-                                Err(_) => {
-                                    errored = true;
-                                    *shelf_error = String::from("Expected a number");
-                                    0
-                                }
-                            };
-                            let basket = match basket_number.parse::<u64>() {
-                                Ok(basket) => basket,
-                                Err(_) => {
-                                    errored = true;
-                                    *basket_error = String::from("Expected a number");
-                                    0
-                                }
-                            };
-
-                            if !errored {
-                                let future = database::insert(
-                                    database,
-                                    rack,
-                                    shelf,
-                                    basket,
-                                    item_name.clone(),
-                                    item_notes.clone()
-                                );
-
-                                Task::perform(future, |x| x)
-                            } else {
-                                Task::none()
-                            }
-
-                        } else {
-                            Task::none()
-                        }
+            Message::LocationChoicesLoaded(choices) => {
+                if let Some(location) = self.location_editor() {
+                    for (level_choices, loaded) in location.choices.iter_mut().zip(choices) {
+                        *level_choices = loaded;
                     }
-                    _ => Task::none(),
                 }
+                Task::none()
             }
-            Message::DatabaseSearchSuccess(pool, item_info) => {
-                self.current_database = Some(pool);
-                match &mut self.screen {
-                    Screen::Search { result, .. } => {
-                        *result = item_info;
-                        Task::none()
-                    }
-                    _ => Task::none(),
+            Message::LocationPush => {
+                if let Some(location) = self.location_editor() {
+                    location.levels.push(LocationLevel::default());
+                    location.choices.push(Vec::new());
                 }
+                self.load_location_choices()
             }
-            Message::DatabaseSearchFailure(pool) => {
-                self.current_database = Some(pool);
+            Message::LocationPop => {
+                if let Some(location) = self.location_editor() && location.levels.len() > 1 {
+                    location.levels.pop();
+                    location.choices.pop();
+                }
                 Task::none()
             }
-            Message::SearchQueryUpdate(query_update) => {
-                match &mut self.screen {
-                    Screen::Search { query, .. } => {
-                        *query = query_update;
-                        Task::none()
-                    }
-                    _ => Task::none(),
+            Message::ItemNameUpdate(name) => {
+                if let Screen::Add { item_name, .. } | Screen::Edit { item_name, .. } = &mut self.screen {
+                    *item_name = name;
+                }
+                Task::none()
+            }
+            Message::ItemNotesUpdate(notes) => {
+                if let Screen::Add { item_notes, .. } | Screen::Edit { item_notes, .. } = &mut self.screen {
+                    *item_notes = notes;
+                }
+                Task::none()
+            }
+            Message::TagInputUpdate(input) => {
+                if let Some(tags) = self.tag_editor() {
+                    tags.input = input;
+                }
+                Task::none()
+            }
+            Message::TagSubmit => {
+                if let Some(tags) = self.tag_editor() {
+                    let input = std::mem::take(&mut tags.input);
+                    tags.add(&input);
+                }
+                Task::none()
+            }
+            Message::TagPicked(tag) => {
+                if let Some(tags) = self.tag_editor() {
+                    tags.add(&tag);
+                    return Task::none();
+                }
+                // a tag chip on the Detail screen, so list everything with that tag
+                let load = self.update(Message::SearchPressed);
+                if let Some(tags) = self.tag_editor() {
+                    tags.add(&tag);
+                }
+                Task::batch([load, self.update(Message::SearchQuery)])
+            }
+            Message::TagRemove(tag) => {
+                if let Some(tags) = self.tag_editor() {
+                    tags.tags.retain(|existing| *existing != tag);
+                }
+                Task::none()
+            }
+            Message::TagsLoaded(known) => {
+                if let Some(tags) = self.tag_editor() {
+                    tags.known = known;
+                }
+                self.load_fields()
+            }
+            Message::FieldValueUpdate(name, value) => {
+                if let Screen::Add { fields, .. } | Screen::Edit { fields, .. } = &mut self.screen {
+                    fields.set(name, value);
+                }
+                Task::none()
+            }
+            Message::FieldsLoaded(loaded) => {
+                match &mut self.screen {
+                    Screen::Add { fields, .. } | Screen::Edit { fields, .. } => {
+                        fields.fields = loaded;
+                    }
+                    Screen::Search { fields, .. }
+                        | Screen::Fields { fields, .. }
+                        | Screen::Import { fields, .. } => {
+                        *fields = loaded;
+                    }
+                    _ => {}
+                }
+                self.load_location_choices()
+            }
+            Message::FieldsChanged => {
+                if let Screen::Fields { name, choices, error, .. } = &mut self.screen {
+                    *name = String::new();
+                    *choices = String::new();
+                    *error = String::new();
+                }
+                self.load_fields()
+            }
+            Message::FieldNameUpdate(update) => {
+                if let Screen::Fields { name, error, .. } = &mut self.screen {
+                    *name = update;
+                    *error = String::new();
+                }
+                Task::none()
+            }
+            Message::FieldKindChanged(update) => {
+                if let Screen::Fields { kind, error, .. } = &mut self.screen {
+                    *kind = update;
+                    *error = String::new();
+                }
+                Task::none()
+            }
+            Message::FieldChoicesUpdate(update) => {
+                if let Screen::Fields { choices, error, .. } = &mut self.screen {
+                    *choices = update;
+                    *error = String::new();
+                }
+                Task::none()
+            }
+            Message::FieldCreate => {
+                let Screen::Fields { fields, name, kind, choices, error } = &mut self.screen else {
+                    return Task::none();
+                };
+                let name = name.trim().to_string();
+                let choices = choices.split(',')
+                    .map(|choice| choice.trim().to_string())
+                    .filter(|choice| !choice.is_empty())
+                    .collect::<Vec<_>>();
+
+                if name.is_empty() {
+                    *error = String::from("The field needs a name");
+                    return Task::none();
+                }
+                if fields.iter().any(|field| field.name.to_lowercase() == name.to_lowercase()) {
+                    *error = format!("There is already a field called {}", name);
+                    return Task::none();
+                }
+                if *kind == FieldKind::Choice && choices.is_empty() {
+                    *error = String::from("A choice field needs at least one choice");
+                    return Task::none();
+                }
+
+                if let Some(database) = self.current_database.clone() {
+                    Task::perform(tasks::create_field(database, name, *kind, choices), |x| x)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::FieldDelete(field_id) => {
+                if let Some(database) = self.current_database.clone() {
+                    Task::perform(tasks::delete_field(database, field_id), |x| x)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::FieldFilterPush => {
+                if let Screen::Search { field_filters, .. } = &mut self.screen {
+                    field_filters.push(FieldFilter::default());
+                }
+                Task::none()
+            }
+            Message::FieldFilterRemove(index) => {
+                if let Screen::Search { field_filters, .. } = &mut self.screen && index < field_filters.len() {
+                    field_filters.remove(index);
+                }
+                Task::none()
+            }
+            Message::FieldFilterPicked(index, name) => {
+                if let Screen::Search { fields, field_filters, .. } = &mut self.screen
+                    && let Some(filter) = field_filters.get_mut(index)
+                    && let Some(field) = fields.iter().find(|field| field.name == name) {
+                    filter.name = name;
+                    filter.kind = field.kind;
+                    filter.value = String::new();
+                }
+                Task::none()
+            }
+            Message::FieldFilterOpChanged(index, op) => {
+                if let Screen::Search { field_filters, .. } = &mut self.screen
+                    && let Some(filter) = field_filters.get_mut(index) {
+                    filter.op = op;
+                }
+                Task::none()
+            }
+            Message::FieldFilterValueUpdate(index, value) => {
+                if let Screen::Search { field_filters, .. } = &mut self.screen
+                    && let Some(filter) = field_filters.get_mut(index) {
+                    filter.value = value;
+                }
+                Task::none()
+            }
+            Message::AddItem => {
+                self.insert_item(false)
+            }
+            Message::IdenticalItemFound(item) => {
+                if let Screen::Add { identical, status, .. } = &mut self.screen {
+                    *status = String::new();
+                    *identical = Some(item);
+                }
+                Task::none()
+            }
+            Message::ItemAdded(change) => {
+                if let Screen::Add { status, .. } = &mut self.screen
+                    && let Change::Added(item) = &change {
+                    *status = format!("Added {} to {}", item.item_name, item.location_path);
+                }
+                self.record_change(change);
+                Task::none()
+            }
+            Message::AddToIdentical => {
+                if let Screen::Add { identical: Some(item), .. } = &self.screen
+                    && let Some(database) = self.current_database.clone() {
+                    return Task::perform(tasks::change_quantity(database, item.item_id, 1), |x| x);
+                }
+                Task::none()
+            }
+            Message::AddSeparately => {
+                self.insert_item(true)
+            }
+            Message::QuantityChange(item_id, by) => {
+                if let Some(database) = self.current_database.clone() {
+                    Task::perform(tasks::change_quantity(database, item_id, by), |x| x)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::QuantityChanged(item_id, quantity, change) => {
+                self.record_change(change);
+                match &mut self.screen {
+                    Screen::Add { identical, status, .. } => {
+                        if let Some(item) = identical.take() {
+                            *status = format!("There are now {} of {}", quantity, item.item_name);
+                        }
+                    }
+                    Screen::Search { result, .. } => {
+                        for hit in result.iter_mut().filter(|hit| hit.item.item_id == item_id) {
+                            hit.item.quantity = quantity;
+                        }
+                    }
+                    Screen::Browse { items, .. } => {
+                        for item in items.iter_mut().filter(|item| item.item_id == item_id) {
+                            item.quantity = quantity;
+                        }
+                    }
+                    Screen::Detail { item: Some(item), .. } if item.item_id == item_id => {
+                        item.quantity = quantity;
+                        return self.load_history(item_id);
+                    }
+                    _ => {}
+                }
+                Task::none()
+            }
+            Message::EditPressed(item_id) => {
+                if let Some(database) = self.current_database.clone() {
+                    Task::perform(tasks::load_edit(database, item_id), |x| x)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::DetailPressed(item_id) => {
+                if let Some(database) = self.current_database.clone() {
+                    self.screen = Screen::Detail {
+                        item_id,
+                        item: None,
+                        location: Vec::new(),
+                        photos: Vec::new(),
+                        thumbnails: Thumbnails::new(),
+                        history: Vec::new(),
+                        confirming_delete: false,
+                        status: String::from("Loading..."),
+                    };
+                    Task::perform(tasks::load_detail(database, item_id), |x| x)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::DetailLoaded(item, location, photos) => {
+                if let Screen::Detail { item_id, item: shown, location: shown_location, photos: shown_photos, thumbnails, status, .. } = &mut self.screen
+                    && *item_id == item.item_id {
+                    *thumbnails = thumbnail_images(photos.iter().map(|photo| &photo.thumbnail));
+                    *shown = Some(item);
+                    *shown_location = location;
+                    *shown_photos = photos;
+                    *status = String::new();
+                    let item_id = *item_id;
+                    return self.load_history(item_id);
+                }
+                Task::none()
+            }
+            Message::EditLoaded(item, location, photos) => {
+                // an undo can put the item being edited in the trash, where it can only be looked at
+                if item.deleted_at.is_some() {
+                    return self.update(Message::DetailPressed(item.item_id));
+                }
+                let location = if location.is_empty() {
+                    LocationEditor::new()
+                } else {
+                    LocationEditor::with_levels(location)
+                };
+                self.screen = Screen::Edit {
+                    item_id: item.item_id,
+                    location,
+                    item_name: item.item_name,
+                    item_notes: item.item_notes,
+                    tags: TagEditor::with_tags(item.tags),
+                    fields: FieldEditor::with_values(item.fields),
+                    thumbnails: thumbnail_images(photos.iter().map(|photo| &photo.thumbnail)),
+                    photos,
+                    status: String::new(),
+                };
+                self.load_tags()
+            }
+            Message::EditSave => {
+                match &mut self.screen {
+                    Screen::Edit {
+                        item_id,
+                        location,
+                        item_name,
+                        item_notes,
+                        tags,
+                        fields,
+                        status,
+                        ..
+                    } => {
+                        let Some(location) = location.validate() else {
+                            return Task::none();
+                        };
+                        let Some(fields) = fields.validate() else {
+                            return Task::none();
+                        };
+
+                        if let Some(database) = self.current_database.clone() {
+                            *status = String::from("Saving...");
+                            let future = tasks::update_item(
+                                database,
+                                *item_id,
+                                location,
+                                item_name.clone(),
+                                item_notes.clone(),
+                                tags.tags.clone(),
+                                fields
+                            );
+
+                            Task::perform(future, |x| x)
+                        } else {
+                            Task::none()
+                        }
+                    }
+                    _ => Task::none(),
+                }
+            }
+            Message::AttachPhotos => {
+                use rfd::AsyncFileDialog;
+
+                let future = async {
+                    let files = AsyncFileDialog::new()
+                        .add_filter("images", &["png", "jpg", "jpeg", "gif", "webp", "bmp"])
+                        .pick_files()
+                        .await;
+
+                    match files {
+                        None => Message::None,
+                        Some(files) => Message::PhotosPicked(
+                            files.iter()
+                                .map(|file| file.path().to_path_buf())
+                                .collect()
+                        ),
+                    }
+                };
+
+                Task::perform(future, |x| x)
+            }
+            Message::PhotosPicked(paths) => {
+                if let Screen::Edit { item_id, status, .. } = &mut self.screen
+                    && let Some(database) = self.current_database.clone() {
+                    *status = String::from("Attaching photos...");
+                    return Task::perform(tasks::attach_photos(database, *item_id, paths), |x| x);
+                }
+                Task::none()
+            }
+            Message::PhotoRemove(photo_id) => {
+                if let Screen::Edit { item_id, .. } = &self.screen
+                    && let Some(database) = self.current_database.clone() {
+                    return Task::perform(tasks::delete_photo(database, *item_id, photo_id), |x| x);
+                }
+                Task::none()
+            }
+            Message::PhotosChanged(item_id) => {
+                match self.current_database.clone() {
+                    Some(database) => Task::perform(tasks::photos(database, item_id), |x| x),
+                    None => Task::none(),
+                }
+            }
+            Message::PhotosLoaded(loaded_id, loaded) => {
+                if let Screen::Edit { item_id, photos, thumbnails, status, .. } = &mut self.screen
+                    && *item_id == loaded_id {
+                    *thumbnails = thumbnail_images(loaded.iter().map(|photo| &photo.thumbnail));
+                    *photos = loaded;
+                    *status = String::new();
+                }
+                Task::none()
+            }
+            Message::ItemUpdated(change) => {
+                self.record_change(change);
+                if let Screen::Edit { status, .. } = &mut self.screen {
+                    *status = String::from("Saved");
+                }
+                Task::none()
+            }
+            Message::DatabaseSearchSuccess(item_info) => {
+                match &mut self.screen {
+                    Screen::Search { result, thumbnails, selected, .. } => {
+                        *thumbnails = thumbnail_images(item_info.iter().filter_map(|item| item.thumbnail.as_ref()));
+                        *result = item_info.into_iter()
+                            .map(SearchHit::from)
+                            .collect();
+                        selected.clear();
+                        Task::none()
+                    }
+                    Screen::Delete { matches, .. } => {
+                        *matches = item_info;
+                        Task::none()
+                    }
+                    _ => Task::none(),
+                }
+            }
+            Message::DatabaseTextSearchSuccess(hits) => {
+                if let Screen::Search { result, thumbnails, selected, .. } = &mut self.screen {
+                    *thumbnails = thumbnail_images(hits.iter().filter_map(|hit| hit.item.thumbnail.as_ref()));
+                    *result = hits;
+                    selected.clear();
+                }
+                Task::none()
+            }
+            Message::DatabaseSearchFailure => {
+                match &mut self.screen {
+                    Screen::Search { result, selected, .. } => {
+                        result.clear();
+                        selected.clear();
+                    }
+                    Screen::Delete { matches, result, .. } => {
+                        matches.clear();
+                        *result = String::from("No items match that name");
+                    }
+                    _ => {}
+                }
+                Task::none()
+            }
+            Message::SearchQueryUpdate(query_update) => {
+                match &mut self.screen {
+                    Screen::Search { query, .. } => {
+                        *query = query_update;
+                        Task::none()
+                    }
+                    _ => Task::none(),
                 }
             }
+            Message::SearchModeChanged(mode_update) => {
+                if let Screen::Search { mode, .. } = &mut self.screen {
+                    *mode = mode_update;
+                }
+                Task::none()
+            }
             Message::SearchQuery => {
-                if let Some(database) = self.current_database.take() {
-                    match &self.screen {
-                        Screen::Search { query, ..} => {
-                            Task::perform(database::search(database, query.clone()), |x| x)
+                if let Some(database) = self.current_database.clone() {
+                    match &mut self.screen {
+                        Screen::Search { query, mode, tags, field_filters, last_search, .. } => {
+                            let search = LastSearch::Query {
+                                query: query.trim().to_string(),
+                                mode: *mode,
+                                filter: SearchFilter {
+                                    tags: tags.tags.clone(),
+                                    fields: field_filters.clone(),
+                                },
+                            };
+                            *last_search = Some(search.clone());
+                            search.run(database)
                         }
                         _ => Task::none(),
                     }
@@ -414,20 +1520,127 @@ impl Catalog {
                     Task::none()
                 }
             }
+            Message::SearchToggleSelected(item_id, checked) => {
+                if let Screen::Search { selected, .. } = &mut self.screen {
+                    selected.retain(|id| *id != item_id);
+                    if checked {
+                        selected.push(item_id);
+                    }
+                }
+                Task::none()
+            }
+            Message::SearchLocationUpdate(index, name) => {
+                if let Screen::Search { location_filter, .. } = &mut self.screen
+                    && let Some(level) = location_filter.get_mut(index) {
+                    *level = name;
+                }
+                Task::none()
+            }
+            Message::SearchLocationPush => {
+                if let Screen::Search { location_filter, .. } = &mut self.screen {
+                    location_filter.push(String::new());
+                }
+                Task::none()
+            }
+            Message::SearchLocationPop => {
+                if let Screen::Search { location_filter, .. } = &mut self.screen && location_filter.len() > 1 {
+                    location_filter.pop();
+                }
+                Task::none()
+            }
+            Message::SearchLocationQuery => {
+                if let Screen::Search { query, location_filter, tags, field_filters, last_search, .. } = &mut self.screen
+                    && let Some(database) = self.current_database.clone() {
+                    let search = LastSearch::Location {
+                        location: location_filter.clone(),
+                        name: query.clone(),
+                        filter: SearchFilter {
+                            tags: tags.tags.clone(),
+                            fields: field_filters.clone(),
+                        },
+                    };
+                    *last_search = Some(search.clone());
+                    return search.run(database);
+                }
+                Task::none()
+            }
+            Message::MoveItem(item_id) => {
+                let items = match &self.screen {
+                    Screen::Search { result, .. } => {
+                        result.iter()
+                            .filter(|hit| hit.item.item_id == item_id)
+                            .map(|hit| hit.item.clone())
+                            .collect()
+                    }
+                    Screen::Detail { item: Some(item), .. } if item.item_id == item_id => {
+                        vec![item.clone()]
+                    }
+                    _ => Vec::new(),
+                };
+                self.start_move(items)
+            }
+            Message::MoveSelected => {
+                let Screen::Search { result, selected, .. } = &self.screen else {
+                    return Task::none();
+                };
+                let items = result.iter()
+                    .filter(|hit| selected.contains(&hit.item.item_id))
+                    .map(|hit| hit.item.clone())
+                    .collect();
+                self.start_move(items)
+            }
+            Message::MoveConfirm => {
+                match &mut self.screen {
+                    Screen::Move { items, location, status } => {
+                        let Some(location) = location.validate() else {
+                            return Task::none();
+                        };
+
+                        if let Some(database) = self.current_database.clone() {
+                            *status = String::from("Moving...");
+                            let item_ids = items.iter()
+                                .map(|item| item.item_id)
+                                .collect();
+                            Task::perform(tasks::move_items(database, item_ids, location), |x| x)
+                        } else {
+                            Task::none()
+                        }
+                    }
+                    _ => Task::none(),
+                }
+            }
+            Message::ItemsMoved(change) => {
+                if let Screen::Move { location, status, .. } = &mut self.screen
+                    && let Change::Moved(moved) = &change {
+                    *status = format!("Moved {} item(s) to {}", moved.len(), location.path().join(" / "));
+                }
+                self.record_change(change);
+                Task::none()
+            }
+            Message::RebuildSearchIndex => {
+                if let Some(database) = self.current_database.clone() {
+                    Task::perform(tasks::rebuild_search_index(database), |x| x)
+                } else {
+                    Task::none()
+                }
+            }
             Message::DeleteQueryUpdate(query_update) => {
                 match &mut self.screen {
-                    Screen::Delete { item_name, .. } => {
+                    Screen::Delete { item_name, result, .. } => {
                         *item_name = query_update;
+                        *result = String::new();
                         Task::none()
                     }
                     _ => Task::none(),
                 }
             }
             Message::DeleteQuery => {
-                if let Some(database) = self.current_database.take() {
-                    match &self.screen {
-                        Screen::Delete { item_name, ..} => {
-                            Task::perform(database::delete(database, item_name.clone()), |x| x)
+                if let Some(database) = self.current_database.clone() {
+                    match &mut self.screen {
+                        Screen::Delete { item_name, confirming, result, .. } => {
+                            *confirming = None;
+                            *result = String::new();
+                            Task::perform(tasks::search(database, item_name.trim().to_string(), SearchMode::Substring, SearchFilter::default()), |x| x)
                         }
                         _ => Task::none(),
                     }
@@ -435,32 +1648,294 @@ impl Catalog {
                     Task::none()
                 }
             }
-            Message::None => {
+            Message::DeleteSelect(item_id) => {
+                match &mut self.screen {
+                    Screen::Delete { matches, confirming, .. } => {
+                        *confirming = matches.iter()
+                            .find(|item| item.item_id == item_id)
+                            .cloned();
+                    }
+                    Screen::Detail { item: Some(item), confirming_delete, .. } if item.item_id == item_id => {
+                        *confirming_delete = true;
+                    }
+                    _ => {}
+                }
                 Task::none()
             }
-        }
-    }
-
-    pub fn view(&self) -> Element<Message> {
-
-        match self.screen {
-            Screen::Starting => self.starting(),
-            Screen::InitializeEmpty(_) => self.initialize_empty(),
-            Screen::InitializeChoice(_) => self.initialize_choice(),
-            Screen::InitializeError(_) => self.initialize_error(),
-            Screen::Welcome => self.welcome(),
-            Screen::Add {..} => self.add(),
-            Screen::Search {..} => self.search(),
-            Screen::Delete {..} => self.delete(),
-        }
-    }
-
-    fn close_events(&self) -> Subscription<Message> {
-        window::close_events().map(|_| Message::Shutdown)
+            Message::DeleteCancel => {
+                match &mut self.screen {
+                    Screen::Delete { confirming, .. } => *confirming = None,
+                    Screen::Detail { confirming_delete, .. } => *confirming_delete = false,
+                    _ => {}
+                }
+                Task::none()
+            }
+            Message::DeleteConfirm => {
+                let item_id = match &self.screen {
+                    Screen::Delete { confirming: Some(item), .. } => item.item_id,
+                    Screen::Detail { item: Some(item), confirming_delete: true, .. } => item.item_id,
+                    _ => return Task::none(),
+                };
+                if let Some(database) = self.current_database.clone() {
+                    return Task::perform(tasks::delete(database, item_id), |x| x);
+                }
+                Task::none()
+            }
+            Message::ItemDeleted(deleted) => {
+                if let Screen::Detail { item, confirming_delete, status, .. } = &mut self.screen {
+                    let name = item.take()
+                        .map(|item| item.item_name)
+                        .unwrap_or_default();
+                    *confirming_delete = false;
+                    *status = match deleted {
+                        None => format!("{} was already gone, nothing was deleted", name),
+                        Some(_) => format!("Deleted {}", name),
+                    };
+                }
+                if let Screen::Delete { matches, confirming, result, .. } = &mut self.screen {
+                    let name = confirming.take()
+                        .map(|item| item.item_name)
+                        .unwrap_or_default();
+                    *result = match deleted {
+                        None => format!("{} was already gone, nothing was deleted", name),
+                        Some(_) => format!("Deleted {}", name),
+                    };
+                    matches.clear();
+                }
+                if let Some(change) = deleted {
+                    self.record_change(change);
+                }
+                Task::none()
+            }
+            Message::BrowseTo(location_id) => {
+                if let Some(database) = self.current_database.clone() {
+                    Task::perform(tasks::browse(database, location_id), |x| x)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::BrowseLoaded(new_trail, new_children, new_items) => {
+                if let Screen::Browse { trail, children, items, thumbnails } = &mut self.screen {
+                    *thumbnails = thumbnail_images(new_items.iter().filter_map(|item| item.thumbnail.as_ref()));
+                    *trail = new_trail;
+                    *children = new_children;
+                    *items = new_items;
+                }
+                Task::none()
+            }
+            Message::None => {
+                Task::none()
+            }
+        }
+    }
+
+    /// Adds the item entered on the Add screen.
+    /// Unless `allow_identical` is set, the user is asked first if the same item is already stored there.
+    fn insert_item(&mut self, allow_identical: bool) -> Task<Message> {
+        let Screen::Add { location, item_name, item_notes, tags, fields, identical, status } = &mut self.screen else {
+            return Task::none();
+        };
+        let Some(location) = location.validate() else {
+            return Task::none();
+        };
+        let Some(fields) = fields.validate() else {
+            return Task::none();
+        };
+
+        if let Some(database) = self.current_database.clone() {
+            *identical = None;
+            *status = String::new();
+            let future = tasks::insert(
+                database,
+                location,
+                item_name.clone(),
+                item_notes.clone(),
+                tags.tags.clone(),
+                fields,
+                allow_identical
+            );
+
+            Task::perform(future, |x| x)
+        } else {
+            Task::none()
+        }
+    }
+
+    /// The location being entered on the current screen, if it has one
+    fn location_editor(&mut self) -> Option<&mut LocationEditor> {
+        match &mut self.screen {
+            Screen::Add { location, .. }
+                | Screen::Edit { location, .. }
+                | Screen::Move { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+
+    /// The tags being entered on the current screen, if it has any
+    fn tag_editor(&mut self) -> Option<&mut TagEditor> {
+        match &mut self.screen {
+            Screen::Add { tags, .. }
+                | Screen::Edit { tags, .. }
+                | Screen::Search { tags, .. } => Some(tags),
+            _ => None,
+        }
+    }
+
+    /// Asks the database for the tags that can be picked on the current screen,
+    /// then for its custom fields and location choices once those have arrived
+    fn load_tags(&mut self) -> Task<Message> {
+        if let Some(database) = self.current_database.clone() {
+            Task::perform(tasks::tag_names(database), |x| x)
+        } else {
+            Task::none()
+        }
+    }
+
+    /// Asks the database for the catalog's custom fields,
+    /// then for the current screen's location choices once those have arrived
+    fn load_fields(&mut self) -> Task<Message> {
+        if let Some(database) = self.current_database.clone() {
+            Task::perform(tasks::fields(database), |x| x)
+        } else {
+            Task::none()
+        }
+    }
+
+    /// Makes `change` the next one to undo.
+    /// Anything undone before can't be redone any more, since it may depend on how things were before `change`.
+    fn record_change(&mut self, change: Change) {
+        self.undo.push(change);
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.controls_status = String::new();
+    }
+
+    /// Reloads whatever the current screen shows from the catalog, after it was changed by an undo or redo
+    fn refresh(&mut self) -> Task<Message> {
+        match &mut self.screen {
+            Screen::Detail { item_id, item, status, .. } => {
+                let item_id = *item_id;
+                *item = None;
+                *status = String::from("Loading...");
+                match self.current_database.clone() {
+                    Some(database) => Task::perform(tasks::load_detail(database, item_id), |x| x),
+                    None => Task::none(),
+                }
+            }
+            Screen::Browse { trail, .. } => {
+                let location_id = trail.last().map(|location| location.location_id);
+                self.update(Message::BrowseTo(location_id))
+            }
+            Screen::Search { last_search: Some(search), .. } => {
+                match self.current_database.clone() {
+                    Some(database) => search.clone().run(database),
+                    None => Task::none(),
+                }
+            }
+            Screen::Edit { item_id, .. } => {
+                let item_id = *item_id;
+                match self.current_database.clone() {
+                    Some(database) => Task::perform(tasks::load_edit(database, item_id), |x| x),
+                    None => Task::none(),
+                }
+            }
+            Screen::Add { identical, status, .. } => {
+                // the item it was about to be added to may be gone now, so ask again on the next Add
+                if identical.take().is_some() {
+                    *status = String::new();
+                }
+                Task::none()
+            }
+            Screen::History { .. } => self.update(Message::HistoryPressed),
+            Screen::Trash { .. } => self.update(Message::TrashPressed),
+            _ => Task::none(),
+        }
+    }
+
+    /// Ctrl+Z to undo and Ctrl+Shift+Z to redo, or Cmd on macOS
+    fn shortcut(key: keyboard::Key, modifiers: keyboard::Modifiers) -> Option<Message> {
+        match key.as_ref() {
+            keyboard::Key::Character(c) if modifiers.command() && c.eq_ignore_ascii_case("z") => {
+                if modifiers.shift() {
+                    Some(Message::Redo)
+                } else {
+                    Some(Message::Undo)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Loads the history shown on the Detail screen of an item
+    fn load_history(&mut self, item_id: i64) -> Task<Message> {
+        if let Some(database) = self.current_database.clone() {
+            Task::perform(tasks::history(database, Some(item_id)), |x| x)
+        } else {
+            Task::none()
+        }
+    }
+
+    /// Switches to the Move screen for `items`, unless there is nothing to move
+    fn start_move(&mut self, items: Vec<ItemInfo>) -> Task<Message> {
+        if items.is_empty() {
+            return Task::none();
+        }
+        self.screen = Screen::Move {
+            items,
+            location: LocationEditor::new(),
+            status: String::new(),
+        };
+        self.load_location_choices()
+    }
+
+    /// Asks the database for the locations that can be picked at each level of the current screen's location
+    fn load_location_choices(&mut self) -> Task<Message> {
+        let Some(location) = self.location_editor() else {
+            return Task::none();
+        };
+        let path = location.path();
+
+        if let Some(database) = self.current_database.clone() {
+            Task::perform(tasks::location_choices(database, path), |x| x)
+        } else {
+            Task::none()
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+
+        match self.screen {
+            Screen::Starting => self.starting(),
+            Screen::InitializeEmpty(_) => self.initialize_empty(),
+            Screen::InitializeChoice(..) => self.initialize_choice(),
+            Screen::InitializeError(_) => self.initialize_error(),
+            Screen::Welcome => self.welcome(),
+            Screen::Add {..} => self.add(),
+            Screen::Edit {..} => self.edit(),
+            Screen::Move {..} => self.move_items(),
+            Screen::Browse {..} => self.browse(),
+            Screen::Detail {..} => self.detail(),
+            Screen::Search {..} => self.search(),
+            Screen::Fields {..} => self.fields(),
+            Screen::History {..} => self.history(),
+            Screen::Trash {..} => self.trash(),
+            Screen::Import {..} => self.import(),
+            Screen::Delete {..} => self.delete(),
+        }
+    }
+
+    fn close_events(&self) -> Subscription<Message> {
+        window::close_events().map(|_| Message::Shutdown)
     }
 
     fn subscriptions(&self) -> Subscription<Message> {
-        Subscription::batch([self.initialize_subscription(), self.close_events()])
+        Subscription::batch([
+            self.initialize_subscription(),
+            self.close_events(),
+            keyboard::on_key_press(Self::shortcut),
+        ])
     }
 
     fn initialize_subscription(&self) -> Subscription<Message> {
@@ -475,12 +1950,9 @@ impl Catalog {
 
         stream::channel(100, |mut output| async move {
 
-            match Catalog::setup_config_dir() {
-                Err(error) => {
-                    let _ = output.send(Message::InitializationFailed(error.to_string() + ": setting config dir")).await;
-                    return;
-                }
-                _ => {}
+            if let Err(error) = Catalog::setup_config_dir() {
+                let _ = output.send(Message::InitializationFailed(error.to_string() + ": setting config dir")).await;
+                return;
             }
             let project_dirs = if let Some(project_dirs) = ProjectDirs::from("org", "Ki11erRabbit", "Catalog") {
                 project_dirs
@@ -494,343 +1966,1263 @@ impl Catalog {
             catalog_toml.push(config_dir);
             catalog_toml.push("databases.toml");
 
-            let mut file = match OpenOptions::new()
-                .read(true)
-                .write(true)
-                .create(true)
-                .open(catalog_toml).await {
-                    Err(error) => {
-                        let _ = output.send(Message::InitializationFailed(error.to_string() + ": opening file")).await;
-                        return;
-                    }
-                    Ok(file) => file,
-                };
+            let mut file = match OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(catalog_toml).await {
+                    Err(error) => {
+                        let _ = output.send(Message::InitializationFailed(error.to_string() + ": opening file")).await;
+                        return;
+                    }
+                    Ok(file) => file,
+                };
+
+            let mut buf = Vec::new();
+            
+            if let Err(err) = file.read_to_end(&mut buf).await {
+                let _ = output.send(Message::InitializationFailed(err.to_string() + ": reading file")).await;
+                return;
+            }
+            drop(file);
+
+            let config_contents = match String::from_utf8(buf) {
+                Err(err) => {
+                    let _ = output.send(Message::InitializationFailed(err.to_string())).await;
+                    return;
+                }
+                Ok(config) => config,
+            };
+
+            let config: Config = match toml::from_str::<Config>(&config_contents) {
+                Err(_) => {
+                    let _ = output.send(Message::InitializationSuccessful(Config::default())).await;
+                    return;
+                }
+                Ok(config) => config,
+            };
+
+            let _ = output.send(Message::InitializationSuccessful(config)).await;
+        })
+    }
+
+    async fn dump_config(config: Config) -> Message {
+        use directories::ProjectDirs;
+        use std::path::PathBuf;
+        use tokio::fs::OpenOptions;
+        use tokio::io::AsyncWriteExt;
+
+        if Catalog::setup_config_dir().is_err() {
+            // TODO: send out notification that getting failed
+            return Message::DumpedConfig;
+        }
+        
+        let project_dirs = if let Some(project_dirs) = ProjectDirs::from("org", "Ki11erRabbit", "Catalog") {
+            project_dirs
+        } else {
+            ProjectDirs::from("org", "Ki11erRabbit", "Catalog")
+                .expect("just created directory but somehow it doesn't exist")
+        };
+
+        let config_dir = project_dirs.config_dir();
+        let mut catalog_toml = PathBuf::new();
+        catalog_toml.push(config_dir);
+        catalog_toml.push("databases.toml");
+
+        let mut file = match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(catalog_toml).await {
+                Err(error) => {
+                    println!("{}", error);
+                    // TODO: report error opening config file
+                    return Message::DumpedConfig;
+                }
+                Ok(file) => file,
+            };
+
+        let config = toml::to_string(&config).expect("todo: handle config serialization");
+
+        file.write_all(config.as_bytes())
+            .await
+            .expect("todo: handle failure writing to config");
+
+        _ = file.flush().await;
+
+        drop(file);
+
+        Message::DumpedConfig
+    }
+
+    fn setup_config_dir_common() -> std::io::Result<directories::UserDirs> {
+        use std::io::Error;
+        use directories::UserDirs;
+        UserDirs::new()
+            .ok_or(Error::other(String::from("Failure to get user directory")))
+    }
+    
+    #[cfg(target_os = "windows")]
+    fn setup_config_dir() -> std::io::Result<()> {
+        use std::path::PathBuf;
+
+        let user_dirs = Self::setup_config_dir_common()?;
+
+        let home_path = user_dirs.home_dir();
+        let mut config_path = PathBuf::new();
+        config_path.push(home_path);
+        config_path.push("AppData");
+        config_path.push("Roaming");
+        config_path.push("Ki11erRabbit");
+        config_path.push("Catalog");
+        config_path.push("config");
+        
+        std::fs::create_dir_all(config_path)
+    }
+    #[cfg(target_os = "macos")]
+    fn setup_config_dir() -> std::io::Result<()> {
+        use std::path::PathBuf;
+
+        let user_dirs = Self::setup_config_dir_common()?;
+
+        let home_path = user_dirs.home_dir();
+        let mut config_path = PathBuf::new();
+        config_path.push(home_path);
+        config_path.push("Library");
+        config_path.push("Application Support");
+        config_path.push("org.Ki11erRabbit.Catalog");
+        
+        std::fs::create_dir_all(config_path)
+    }
+    #[cfg(target_os = "linux")]
+    fn setup_config_dir() -> std::io::Result<()> {
+        use std::path::PathBuf;
+
+        let user_dirs = Self::setup_config_dir_common()?;
+
+        let home_path = user_dirs.home_dir();
+        let mut config_path = PathBuf::new();
+        config_path.push(home_path);
+        config_path.push(".config");
+        config_path.push("catalog");
+        
+        std::fs::create_dir_all(config_path)
+    }
+
+    pub fn new() -> Self {
+        Catalog {
+            screen: Screen::Starting,
+            config: Config::default(),
+            current_database: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            reverting: false,
+            controls_status: String::new(),
+            //toasts: Vec::new(),
+        }
+    }
+
+    fn get_controls(&self) -> Column<'_, Message> {
+        let controls =
+            row![
+                horizontal_space(),
+                padded_button("Welcome")
+                    .on_press(Message::WelcomePressed),
+                horizontal_space(),
+                padded_button("Add")
+                    .on_press(Message::AddPressed),
+                horizontal_space(),
+                padded_button("Search")
+                    .on_press(Message::SearchPressed),
+                horizontal_space(),
+                padded_button("Browse")
+                    .on_press(Message::BrowsePressed),
+                horizontal_space(),
+                padded_button("Delete")
+                    .on_press(Message::DeletePressed),
+                horizontal_space(),
+                padded_button("Fields")
+                    .on_press(Message::FieldsPressed),
+                horizontal_space(),
+                padded_button("History")
+                    .on_press(Message::HistoryPressed),
+                horizontal_space(),
+                padded_button("Trash")
+                    .on_press(Message::TrashPressed),
+                horizontal_space(),
+                padded_button("Import")
+                    .on_press(Message::ImportPressed),
+                horizontal_space(),
+                padded_button("Export")
+                    .on_press(Message::ExportPressed),
+                horizontal_space(),
+                padded_button("Undo")
+                    .on_press_maybe((!self.reverting && !self.undo.is_empty()).then_some(Message::Undo)),
+                horizontal_space(),
+                padded_button("Redo")
+                    .on_press_maybe((!self.reverting && !self.redo.is_empty()).then_some(Message::Redo)),
+                horizontal_space(),
+                padded_button("Save and Exit")
+                    .on_press(Message::Shutdown),
+                horizontal_space(),
+            ];
+
+        column![controls]
+            .push_maybe(
+                (!self.controls_status.is_empty()).then(|| text(self.controls_status.as_str()))
+            )
+            .align_x(Alignment::Center)
+    }
+
+    fn starting(&self) -> Element<'_, Message> {
+        let controls = self.get_controls();
+        let contents = Self::container("Starting")
+            .push(
+                "we are waiting for things to start so please be patient"
+            );
+
+        let content: Element<_> = column![controls, contents]
+            .into();
+        content
+    }
+
+    fn initialize_empty(&self) -> Element<'_, Message> {
+        let Screen::InitializeEmpty(input_value) = &self.screen else {
+            unreachable!("already checked for screen state");
+        };
+
+        let input = text_input("Enter absolute path for database", input_value)
+            .id("new-database")
+            .on_input(Message::InitializeInputChanged)
+            .on_submit(Message::InitializeSubmit);
+
+
+        let file_picker_button = padded_button("open file picker")
+            .on_press(Message::InitializeOpenFilePicker);
+
+        let submit_input_button = padded_button("Create Database")
+            .on_press(Message::InitializeSubmit);
+
+        let buttons = column![submit_input_button, file_picker_button];
+        
+        let contents = Self::container("Create a new database")
+            .push(
+                "Create a new database to begin"
+            )
+            .push(
+                row![input, buttons]
+                );
+
+
+        let content: Element<_> = column![contents]
+            .into();
+        content
+    }
+
+    fn initialize_choice(&self) -> Element<'_, Message> {
+        let Screen::InitializeChoice(input_value, error) = &self.screen else {
+            unreachable!("already checked for screen state");
+        };
+
+        let input = text_input("Enter absolute path for database", input_value)
+            .id("new-database")
+            .on_input(Message::InitializeInputChanged)
+            .on_submit(Message::InitializeSubmit);
+
+
+        let file_picker_button = padded_button("open file picker")
+            .on_press(Message::InitializeOpenFilePicker);
+
+        let submit_input_button = padded_button("Create Database")
+            .on_press(Message::InitializeSubmit);
+
+        let buttons = column![submit_input_button, file_picker_button];
+        
+        let contents = Self::container("Create a new database")
+            .push(
+                "Create a new database to begin"
+            )
+            .push(
+                row![input, buttons]
+                );
+
+        let mut database_list = column![];
+            
+        for config in self.config.database_paths.iter() {
+            use std::path::Path;
+            let text = text(config.as_str());
+
+            let (button_text, on_press) = if Path::new(config).exists() {
+                ("Open Database", Message::OpenDatabase(config.clone()))
+            } else {
+                ("Create Database", Message::CreateDatabase(config.clone()))
+            };
+
+            let button = padded_button(button_text)
+                .on_press(on_press);
+
+            database_list = database_list.push(row![text, button]);
+        }
+
+        let content: Element<_> = column![database_list, text(error.as_str()), contents]
+            .into();
+        content
+    }
+
+    fn initialize_error(&self) -> Element<'_, Message> {
+        let Screen::InitializeError(msg) = &self.screen else {
+            panic!("calling initialize_error when not set to proper state");
+        };
+        let contents = Self::container("Initialization Error")
+            .push(
+                msg.as_str()
+            );
+
+        let content: Element<_> = column![contents]
+            .into();
+        content
+    }
+
+    fn welcome(&self) -> Element<'_, Message> {
+        let controls = self.get_controls();
+        let contents = Self::container("Welcome!")
+            .push(
+                "This is a simple cataloging software, driven by sqlite"
+            );
+
+        let content: Element<_> = column![controls, contents]
+            .into();
+        content
+    }
+
+    fn add(&self) -> Element<'_, Message> {
+        let Screen::Add {
+            location,
+            item_name,
+            item_notes,
+            tags,
+            fields,
+            identical,
+            status,
+        } = &self.screen else {
+            unreachable!("should have already checked for this state");
+        };
+        let controls = self.get_controls();
+        let mut contents = Self::container("Add")
+            .push(
+                "This is a simple cataloging software, driven by sqlite"
+            )
+            .push(
+                Self::location_form(location)
+            )
+            .push(
+                row![
+                    Self::pair_input_text("Enter item name", item_name.as_str(), "", Message::ItemNameUpdate),
+                    Self::pair_input_text("Enter item notes", item_notes.as_str(), "", Message::ItemNotesUpdate),
+                    horizontal_space(),
+                    padded_button("Insert").on_press(Message::AddItem)
+                ]
+            )
+            .push(
+                Self::tag_form("Tags", tags)
+            )
+            .push(
+                Self::field_form(fields)
+            );
+
+        if let Some(item) = identical {
+            contents = contents
+                .push(
+                    text(format!("{} is already stored in {}, {} of them", item.item_name, item.location_path, item.quantity)).size(20)
+                )
+                .push(
+                    row![
+                        padded_button("Add one more of it").on_press(Message::AddToIdentical),
+                        padded_button("Add as a separate item").on_press(Message::AddSeparately),
+                    ]
+                    .spacing(10)
+                );
+        }
+        if !status.is_empty() {
+            contents = contents.push(text(status.as_str()));
+        }
+
+        let content: Element<_> = column![controls, contents]
+            .into();
+        content
+    }
+
+    fn edit(&self) -> Element<'_, Message> {
+        let Screen::Edit {
+            location,
+            item_name,
+            item_notes,
+            tags,
+            fields,
+            photos,
+            thumbnails,
+            status,
+            ..
+        } = &self.screen else {
+            unreachable!("should have already checked for this state");
+        };
+        let controls = self.get_controls();
+        let contents = Self::container("Edit")
+            .push(
+                Self::location_form(location)
+            )
+            .push(
+                row![
+                    Self::pair_input_text("Enter item name", item_name.as_str(), "", Message::ItemNameUpdate),
+                    Self::pair_input_text("Enter item notes", item_notes.as_str(), "", Message::ItemNotesUpdate),
+                    horizontal_space(),
+                    padded_button("Save").on_press(Message::EditSave)
+                ]
+            )
+            .push(
+                Self::tag_form("Tags", tags)
+            )
+            .push(
+                Self::field_form(fields)
+            )
+            .push(
+                Self::photo_form(photos, thumbnails)
+            )
+            .push(
+                text(status.as_str())
+            );
+        let content: Element<_> = column![controls, contents]
+            .into();
+        content
+    }
+
+    /// The inputs for entering a location one level at a time
+    fn location_form(location: &LocationEditor) -> Column<'_, Message> {
+        let mut form = column![
+            text("Location, from the outermost place inward").size(20)
+        ];
+
+        for (index, (level, choices)) in location.levels.iter().zip(location.choices.iter()).enumerate() {
+            let names = choices.iter()
+                .map(|choice| choice.name.clone())
+                .collect::<Vec<_>>();
+            let picked = names.iter()
+                .find(|name| **name == level.name)
+                .cloned();
+
+            form = form
+                .push(
+                    row![
+                        Self::pair_input_text("Enter kind (e.g. Room, Shelf, Bin)", level.kind.as_str(), "", move |kind| Message::LocationKindUpdate(index, kind)),
+                        Self::pair_input_text("Enter name", level.name.as_str(), "", move |name| Message::LocationNameUpdate(index, name)),
+                        column![
+                            text("Or pick an existing one"),
+                            pick_list(names, picked, move |name| Message::LocationPicked(index, name))
+                                .placeholder("Existing locations"),
+                        ],
+                        Self::pair_input_text("Enter description (optional)", level.description.as_str(), "", move |description| Message::LocationDescriptionUpdate(index, description)),
+                    ]
+                );
+        }
+
+        form.push(
+            row![
+                text(location.error.as_str()),
+                horizontal_space(),
+                padded_button("Add level").on_press(Message::LocationPush),
+                padded_button("Remove level").on_press(Message::LocationPop),
+            ]
+        )
+    }
+
+    /// The tags entered so far, each with a button to take it off again,
+    /// and the inputs for adding a new tag or picking one that is already in use
+    fn tag_form<'a>(title: &'a str, tags: &'a TagEditor) -> Column<'a, Message> {
+        let mut chips = row![].spacing(5);
+        for tag in tags.tags.iter() {
+            chips = chips.push(
+                button(text(format!("{} x", tag)).size(14))
+                    .style(button::secondary)
+                    .on_press(Message::TagRemove(tag.clone()))
+            );
+        }
+
+        column![
+            text(title).size(20),
+            chips,
+            row![
+                text_input("New tag", tags.input.as_str())
+                    .on_input(Message::TagInputUpdate)
+                    .on_submit(Message::TagSubmit)
+                    .width(200),
+                padded_button("Add tag").on_press(Message::TagSubmit),
+                pick_list(tags.unused(), None::<String>, Message::TagPicked)
+                    .placeholder("Existing tags"),
+            ]
+            .spacing(5)
+            .align_y(Alignment::Center),
+        ]
+        .spacing(5)
+    }
+
+    /// An input for each of the catalog's custom fields, suited to the kind of value it holds
+    fn field_form(fields: &FieldEditor) -> Column<'_, Message> {
+        let mut form = column![
+            text("Fields").size(20)
+        ]
+        .spacing(5);
 
-            let mut buf = Vec::new();
-            
-            match file.read_to_end(&mut buf).await {
-                Err(err) => {
-                    let _ = output.send(Message::InitializationFailed(err.to_string() + ": reading file")).await;
-                    return;
-                }
-                _ => {}
-            }
-            drop(file);
+        if fields.fields.is_empty() {
+            return form.push(text("This catalog has no custom fields yet, they can be added on the Fields screen"));
+        }
 
-            let config_contents = match String::from_utf8(buf) {
-                Err(err) => {
-                    let _ = output.send(Message::InitializationFailed(err.to_string())).await;
-                    return;
+        for field in fields.fields.iter() {
+            let value = fields.value(&field.name);
+            let name = field.name.clone();
+            let input: Element<'_, Message> = match field.kind {
+                FieldKind::Choice => {
+                    let clear = field.name.clone();
+                    row![
+                        pick_list(field.choices.as_slice(), field.choices.iter().find(|choice| *choice == value), move |choice| Message::FieldValueUpdate(name.clone(), choice))
+                            .placeholder("Not set"),
+                        button(text("Clear")).on_press(Message::FieldValueUpdate(clear, String::new())),
+                    ]
+                    .spacing(5)
+                    .into()
                 }
-                Ok(config) => config,
-            };
-
-            let config: Config = match toml::from_str::<Config>(&config_contents) {
-                Err(_) => {
-                    let _ = output.send(Message::InitializationSuccessful(Config::default())).await;
-                    return;
+                kind => {
+                    let placeholder = match kind {
+                        FieldKind::Number => "Number",
+                        FieldKind::Date => "YYYY-MM-DD",
+                        _ => "",
+                    };
+                    text_input(placeholder, value)
+                        .on_input(move |value| Message::FieldValueUpdate(name.clone(), value))
+                        .width(300)
+                        .into()
                 }
-                Ok(config) => config,
             };
 
-            let _ = output.send(Message::InitializationSuccessful(config)).await;
-        })
-    }
+            form = form.push(
+                row![
+                    text(field.name.as_str()).width(150),
+                    input,
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center)
+            );
+        }
 
-    async fn dump_config(config: Config) -> Message {
-        use directories::ProjectDirs;
-        use std::path::PathBuf;
-        use tokio::fs::OpenOptions;
-        use tokio::io::AsyncWriteExt;
+        form.push(text(fields.error.as_str()))
+    }
 
-        match Catalog::setup_config_dir() {
-            Err(error) => {
-                // TODO: send out notification that getting failed
-                return Message::DumpedConfig;
-            }
-            _ => {}
+    /// The thumbnails of an item's photos, each with a button to remove it, and a button for attaching more
+    fn photo_form<'a>(photos: &'a [PhotoInfo], images: &'a Thumbnails) -> Column<'a, Message> {
+        let mut thumbnails = row![].spacing(10);
+        for photo in photos.iter() {
+            thumbnails = thumbnails.push(
+                column![]
+                    .spacing(5)
+                    .push_maybe(
+                        images.get(&photo.thumbnail.photo_id).map(|handle| {
+                            image(handle.clone())
+                                .width(thumbnail::SIZE as f32)
+                                .height(thumbnail::SIZE as f32)
+                        })
+                    )
+                    .push(text(photo.file_name.as_str()).size(12))
+                    .push(button(text("Remove")).on_press(Message::PhotoRemove(photo.photo_id)))
+            );
         }
-        
-        let project_dirs = if let Some(project_dirs) = ProjectDirs::from("org", "Ki11erRabbit", "Catalog") {
-            project_dirs
-        } else {
-            ProjectDirs::from("org", "Ki11erRabbit", "Catalog")
-                .expect("just created directory but somehow it doesn't exist")
-        };
 
-        let config_dir = project_dirs.config_dir();
-        let mut catalog_toml = PathBuf::new();
-        catalog_toml.push(config_dir);
-        catalog_toml.push("databases.toml");
+        column![
+            text(format!("Photos ({})", photos.len())).size(20),
+            scrollable(thumbnails).direction(scrollable::Direction::Horizontal(scrollable::Scrollbar::default())),
+            padded_button("Attach photos").on_press(Message::AttachPhotos),
+        ]
+        .spacing(5)
+    }
 
-        let mut file = match OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(catalog_toml).await {
-                Err(error) => {
-                    println!("{}", error);
-                    // TODO: report error opening config file
-                    return Message::DumpedConfig;
+    /// The rows of custom field comparisons on the Search screen
+    fn field_filter_form<'a>(fields: &'a [FieldInfo], field_filters: &'a [FieldFilter]) -> Column<'a, Message> {
+        let mut form = column![
+            text("Only items whose fields match").size(20)
+        ]
+        .spacing(5);
+
+        let names = fields.iter()
+            .map(|field| field.name.clone())
+            .collect::<Vec<_>>();
+
+        for (index, filter) in field_filters.iter().enumerate() {
+            let picked = names.iter()
+                .find(|name| **name == filter.name)
+                .cloned();
+            let choices = fields.iter()
+                .find(|field| field.name == filter.name && field.kind == FieldKind::Choice)
+                .map(|field| field.choices.as_slice());
+
+            let value: Element<'_, Message> = match choices {
+                Some(choices) => {
+                    pick_list(choices, choices.iter().find(|choice| **choice == filter.value), move |choice| Message::FieldFilterValueUpdate(index, choice))
+                        .placeholder("Value")
+                        .into()
+                }
+                None => {
+                    let placeholder = match filter.kind {
+                        FieldKind::Date => "YYYY-MM-DD",
+                        _ => "Value",
+                    };
+                    // nothing can be typed until a field has been picked
+                    text_input(placeholder, filter.value.as_str())
+                        .on_input_maybe(picked.is_some().then_some(move |value| Message::FieldFilterValueUpdate(index, value)))
+                        .width(200)
+                        .into()
                 }
-                Ok(file) => file,
             };
 
-        let config = toml::to_string(&config).expect("todo: handle config serialization");
-
-        file.write_all(config.as_bytes())
-            .await
-            .expect("todo: handle failure writing to config");
+            form = form.push(
+                row![
+                    pick_list(names.clone(), picked, move |name| Message::FieldFilterPicked(index, name))
+                        .placeholder("Field"),
+                    pick_list(FieldOp::ALL, Some(filter.op), move |op| Message::FieldFilterOpChanged(index, op)),
+                    value,
+                    button(text("Remove")).on_press(Message::FieldFilterRemove(index)),
+                ]
+                .spacing(5)
+                .align_y(Alignment::Center)
+            );
+        }
 
-        _ = file.flush().await;
+        form.push(
+            padded_button("Add field filter").on_press(Message::FieldFilterPush)
+        )
+    }
 
-        drop(file);
+    /// The custom fields an item has values for, e.g. "Grade: 9.8, Issue: 27"
+    fn field_text(item: &ItemInfo) -> String {
+        item.fields.iter()
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 
-        Message::DumpedConfig
+    /// How many of an item there are, with buttons for adding or taking away one
+    fn quantity_control(item: &ItemInfo) -> Row<'_, Message> {
+        row![
+            text(format!("Quantity: {}", item.quantity)),
+            button(text("-")).on_press_maybe(
+                (item.quantity > 1).then_some(Message::QuantityChange(item.item_id, -1))
+            ),
+            button(text("+")).on_press(Message::QuantityChange(item.item_id, 1)),
+        ]
+        .spacing(5)
+        .align_y(Alignment::Center)
     }
 
-    fn setup_config_dir_common() -> std::io::Result<directories::UserDirs> {
-        use std::io::{Error, ErrorKind};
-        use directories::UserDirs;
-        UserDirs::new()
-            .ok_or(Error::new(ErrorKind::Other, String::from("Failure to get user directory")))
+    /// An item's tags as small buttons that add the tag to the search filter,
+    /// or search for everything with that tag when pressed outside the Search screen
+    fn tag_chips(tags: &[String]) -> Row<'_, Message> {
+        let mut chips = row![].spacing(5);
+        for tag in tags.iter() {
+            chips = chips.push(
+                button(text(tag.as_str()).size(12))
+                    .padding([2, 8])
+                    .style(button::secondary)
+                    .on_press(Message::TagPicked(tag.clone()))
+            );
+        }
+        chips
     }
-    
-    #[cfg(target_os = "windows")]
-    fn setup_config_dir() -> std::io::Result<()> {
-        use std::path::PathBuf;
 
-        let user_dirs = Self::setup_config_dir_common()?;
+    fn search(&self) -> Element<'_, Message> {
+        let controls = self.get_controls();
+        let Screen::Search { query, mode, result, thumbnails, selected, location_filter, tags, fields, field_filters, .. } = &self.screen else {
+            unreachable!("already checked for search state but incorrect");
+        };
+        let mut contents = Self::container("Search")
+            .push(
+                "This is a simple cataloging software, driven by sqlite"
+            )
+            .push(
+                row![
+                    Self::pair_input_text("Enter Item Name", query.as_str(), "", Message::SearchQueryUpdate),
+                    column![
+                        text("Match"),
+                        pick_list(SearchMode::ALL, Some(*mode), Message::SearchModeChanged),
+                    ],
+                    padded_button("Search").on_press(Message::SearchQuery),
+                    horizontal_space(),
+                    padded_button("Rebuild search index").on_press(Message::RebuildSearchIndex),
+                ]
+            );
 
-        let home_path = user_dirs.home_dir();
-        let mut config_path = PathBuf::new();
-        config_path.push(home_path);
-        config_path.push("AppData");
-        config_path.push("Roaming");
-        config_path.push("Ki11erRabbit");
-        config_path.push("Catalog");
-        config_path.push("config");
-        
-        std::fs::create_dir_all(config_path)
-    }
-    #[cfg(target_os = "macos")]
-    fn setup_config_dir() -> std::io::Result<()> {
-        use std::path::PathBuf;
+        let mut filter = row![].spacing(5).align_y(Alignment::Center);
+        for (index, level) in location_filter.iter().enumerate() {
+            if index > 0 {
+                filter = filter.push(text("/"));
+            }
+            filter = filter.push(
+                text_input("Any", level.as_str())
+                    .on_input(move |name| Message::SearchLocationUpdate(index, name))
+                    .width(120)
+            );
+        }
+        contents = contents
+            .push(
+                text("Or find the items in a location, leaving a level empty to match any location there")
+            )
+            .push(
+                row![
+                    filter,
+                    horizontal_space(),
+                    padded_button("Add level").on_press(Message::SearchLocationPush),
+                    padded_button("Remove level").on_press(Message::SearchLocationPop),
+                    padded_button("Find in location").on_press(Message::SearchLocationQuery),
+                ]
+                .spacing(5)
+            )
+            .push(
+                Self::tag_form("Only items with all of these tags", tags)
+            );
 
-        let user_dirs = Self::setup_config_dir_common()?;
+        if !fields.is_empty() {
+            contents = contents.push(
+                Self::field_filter_form(fields, field_filters)
+            );
+        }
 
-        let home_path = user_dirs.home_dir();
-        let mut config_path = PathBuf::new();
-        config_path.push(home_path);
-        config_path.push("Library");
-        config_path.push("Application Support");
-        config_path.push("org.Ki11erRabbit.Catalog");
+        let mut added_result = false;
+        let mut results = column![];
+        for hit in result.iter() {
+            let item = &hit.item;
+            if !added_result {
+                contents = contents
+                    .push(
+                        text("Results").size(20)
+                    );
+                added_result = true;
+            }
+            let name = hit.name.as_deref().unwrap_or(item.item_name.as_str());
+            let mut entry = column![
+                text(format!("Location: {}", item.location_path)),
+                Self::highlighted_text("Name: ", name),
+                Self::quantity_control(item),
+            ];
+            if let Some(notes) = &hit.notes {
+                entry = entry.push(Self::highlighted_text("Notes: ", notes));
+            }
+            if !item.fields.is_empty() {
+                entry = entry.push(text(Self::field_text(item)));
+            }
+            if !item.tags.is_empty() {
+                entry = entry.push(Self::tag_chips(&item.tags));
+            }
+            entry = entry.push(
+                row![
+                    checkbox("Select", selected.contains(&item.item_id))
+                        .on_toggle(move |checked| Message::SearchToggleSelected(item.item_id, checked)),
+                    padded_button("Details").on_press(Message::DetailPressed(item.item_id)),
+                    padded_button("Edit").on_press(Message::EditPressed(item.item_id)),
+                    padded_button("Move").on_press(Message::MoveItem(item.item_id)),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center)
+            );
+            results = results
+                .push(
+                    row![]
+                        .spacing(10)
+                        .push_maybe(
+                            item.thumbnail.as_ref()
+                                .and_then(|thumbnail| thumbnails.get(&thumbnail.photo_id))
+                                .map(|handle| image(handle.clone()).width(96).height(96))
+                        )
+                        .push(entry.push(horizontal_space()))
+                );
+        };
+        if added_result {
+            // only show results if there were any
+            if !selected.is_empty() {
+                contents = contents.push(
+                    padded_button("Move selected").on_press(Message::MoveSelected)
+                );
+            }
+            contents = contents.push(
+                scrollable(results));
+        }
         
-        std::fs::create_dir_all(config_path)
+        let content: Element<_> = column![controls, contents]
+            .into();
+        content
     }
-    #[cfg(target_os = "linux")]
-    fn setup_config_dir() -> std::io::Result<()> {
-        use std::path::PathBuf;
 
-        let user_dirs = Self::setup_config_dir_common()?;
+    fn detail(&self) -> Element<'_, Message> {
+        let Screen::Detail { item, location, photos, thumbnails: images, history, confirming_delete, status, .. } = &self.screen else {
+            unreachable!("should have already checked for this state");
+        };
+        let controls = self.get_controls();
 
-        let home_path = user_dirs.home_dir();
-        let mut config_path = PathBuf::new();
-        config_path.push(home_path);
-        config_path.push(".config");
-        config_path.push("catalog");
-        
-        std::fs::create_dir_all(config_path)
-    }
+        let Some(item) = item else {
+            let contents = Self::container("Item")
+                .push(text(status.as_str()).size(20));
+            let content: Element<_> = column![controls, contents]
+                .into();
+            return content;
+        };
 
-    pub fn new() -> Self {
-        Catalog {
-            screen: Screen::Starting,
-            config: Config::default(),
-            current_database: None,
-            //toasts: Vec::new(),
+        let location = location.iter()
+            .map(|level| format!("{} {}", level.kind, level.name))
+            .collect::<Vec<_>>()
+            .join(" / ");
+
+        let quantity: Element<_> = if item.deleted_at.is_some() {
+            text(format!("Quantity: {}", item.quantity)).into()
+        } else {
+            Self::quantity_control(item).into()
+        };
+        let mut details = column![
+            text(format!("Location: {}", location)),
+            quantity,
+            text(format!("Notes: {}", item.item_notes)),
+        ]
+        .spacing(10);
+
+        if !item.tags.is_empty() {
+            details = details.push(
+                row![text("Tags:"), Self::tag_chips(&item.tags)]
+                    .spacing(5)
+                    .align_y(Alignment::Center)
+            );
         }
-    }
+        for (name, value) in item.fields.iter() {
+            details = details.push(text(format!("{}: {}", name, value)));
+        }
+        details = details
+            .push(
+                text(format!("Added: {}", Self::timestamp_text(&item.created_at)))
+            )
+            .push(
+                text(format!("Last changed: {}", Self::timestamp_text(&item.updated_at)))
+            )
+            .push_maybe(
+                item.deleted_at.as_ref().map(|deleted_at| text(format!("In the trash since: {} UTC", deleted_at)))
+            );
 
-    fn get_controls(&self) -> Row<'_, Message> {
-        let controls =
-            row![
-                horizontal_space(),
-                padded_button("Welcome")
-                    .on_press(Message::WelcomePressed),
-                horizontal_space(),
-                padded_button("Add")
-                    .on_press(Message::AddPressed),
-                horizontal_space(),
-                padded_button("Search")
-                    .on_press(Message::SearchPressed),
-                horizontal_space(),
-                padded_button("Delete")
-                    .on_press(Message::DeletePressed),
-                horizontal_space(),
-                padded_button("Save and Exit")
-                    .on_press(Message::Shutdown),
-                horizontal_space(),
-            ];
+        if !photos.is_empty() {
+            let mut thumbnails = row![].spacing(10);
+            for handle in photos.iter().filter_map(|photo| images.get(&photo.thumbnail.photo_id)) {
+                thumbnails = thumbnails.push(
+                    image(handle.clone())
+                        .width(thumbnail::SIZE as f32)
+                        .height(thumbnail::SIZE as f32)
+                );
+            }
+            details = details.push(
+                scrollable(thumbnails).direction(scrollable::Direction::Horizontal(scrollable::Scrollbar::default()))
+            );
+        }
 
-        controls
-    }
+        let actions = if item.deleted_at.is_some() {
+            row![
+                padded_button("Restore").on_press(Message::RestoreItem(item.item_id)),
+            ]
+        } else if *confirming_delete {
+            row![
+                text(if item.quantity > 1 {
+                    format!("This will move all {} of {} to the trash", item.quantity, item.item_name)
+                } else {
+                    format!("This will move {} to the trash", item.item_name)
+                }),
+                padded_button("Delete it").on_press(Message::DeleteConfirm),
+                padded_button("Cancel").on_press(Message::DeleteCancel),
+            ]
+        } else {
+            row![
+                padded_button("Edit").on_press(Message::EditPressed(item.item_id)),
+                padded_button("Move").on_press(Message::MoveItem(item.item_id)),
+                padded_button("Delete").on_press(Message::DeleteSelect(item.item_id)),
+            ]
+        };
 
-    fn starting(&self) -> Element<Message> {
-        let controls = self.get_controls();
-        let contents = Self::container("Starting")
+        let mut contents = Self::container(item.item_name.as_str())
+            .push(details)
             .push(
-                "we are waiting for things to start so please be patient"
-            );
+                actions
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+            )
+            .push(text(status.as_str()));
+
+        if !history.is_empty() {
+            contents = contents
+                .push(
+                    text("History").size(20)
+                )
+                .push(
+                    scrollable(Self::history_list(history, false)).height(200)
+                );
+        }
 
         let content: Element<_> = column![controls, contents]
             .into();
         content
     }
 
-    fn initialize_empty(&self) -> Element<Message> {
-        let Screen::InitializeEmpty(input_value) = &self.screen else {
-            unreachable!("already checked for screen state");
+    /// The entries of a history, naming the item of each when `with_item` is set
+    fn history_list(entries: &[HistoryEntry], with_item: bool) -> Column<'_, Message> {
+        let mut list = column![].spacing(5);
+        for entry in entries.iter() {
+            let mut line = row![
+                text(format!("{} UTC", entry.happened_at)).width(200),
+                text(entry.action.to_string()).width(80),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center);
+            if with_item {
+                // purged items can't be shown any more
+                let name = button(text(entry.item_name.as_str()))
+                    .style(button::secondary)
+                    .on_press_maybe(
+                        (entry.action != HistoryAction::Purged).then_some(Message::DetailPressed(entry.item_id))
+                    );
+                line = line.push(name);
+            }
+            line = line
+                .push(text(entry.details.as_str()))
+                .push(horizontal_space())
+                .push(text(format!("by {}", entry.actor)));
+            list = list.push(line);
+        }
+        list
+    }
+
+    fn import(&self) -> Element<'_, Message> {
+        let Screen::Import { file_name, file, roles, fields, rows, status } = &self.screen else {
+            unreachable!("should have already checked for this state");
         };
+        let controls = self.get_controls();
 
-        let input = text_input("Enter absolute path for database", input_value)
-            .id("new-database")
-            .on_input(Message::InitializeInputChanged)
-            .on_submit(Message::InitializeSubmit);
+        let mut contents = Self::container("Import")
+            .push(
+                "Add many items at once from a CSV file whose first line names its columns. Pick what each column holds, check the preview, then import. Locations that don't exist yet are created, and nothing is added unless every line can be."
+            )
+            .push(
+                row![
+                    padded_button("Choose a CSV file").on_press(Message::ImportPickFile),
+                    text(file_name.as_str()),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center)
+            );
 
+        if !file.headers.is_empty() {
+            // each column with what it holds and its first few values, so the right role is easy to pick
+            let mut columns = row![].spacing(10);
+            for (index, header) in file.headers.iter().enumerate() {
+                let mut column = column![
+                    text(header.as_str()).size(18),
+                    pick_list(
+                        import::ColumnRole::all(fields),
+                        roles.get(index).cloned(),
+                        move |role| Message::ImportRoleChanged(index, role),
+                    ),
+                ]
+                .spacing(5)
+                .width(180);
+                for (_, values) in file.records.iter().take(IMPORT_SAMPLE_LINES) {
+                    column = column.push(text(values.get(index).cloned().unwrap_or_default()));
+                }
+                columns = columns.push(column);
+            }
+            contents = contents.push(
+                scrollable(columns).direction(scrollable::Direction::Horizontal(scrollable::Scrollbar::default()))
+            );
 
-        let file_picker_button = padded_button("open file picker")
-            .on_press(Message::InitializeOpenFilePicker);
+            let errors = rows.iter()
+                .filter(|row| row.is_err())
+                .count();
+            let problem = import::check_roles(roles)
+                .or_else(|| (errors > 0).then(|| format!("{} line(s) have problems, fix them in the file or pick different columns", errors)));
 
-        let submit_input_button = padded_button("Create Database")
-            .on_press(Message::InitializeSubmit);
+            // every line with a problem comes first, since any one of them stops the import
+            let mut preview = column![].spacing(5);
+            for err in rows.iter().filter_map(|row| row.as_ref().err()) {
+                preview = preview.push(text(err.as_str()).style(text::danger));
+            }
+            let ready = rows.iter()
+                .filter_map(|row| row.as_ref().ok())
+                .collect::<Vec<_>>();
+            for row in ready.iter().take(IMPORT_PREVIEW_LINES) {
+                let location = row.item.location.iter()
+                    .map(|level| level.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" / ");
+                preview = preview.push(text(format!("Line {}: {} x{} in {}", row.line, row.item.name, row.item.quantity, location)));
+            }
+            if ready.len() > IMPORT_PREVIEW_LINES {
+                preview = preview.push(text(format!("and {} more", ready.len() - IMPORT_PREVIEW_LINES)));
+            }
 
-        let buttons = column![submit_input_button, file_picker_button];
-        
-        let contents = Self::container("Create a new database")
-            .push(
-                "Create a new database to begin"
-            )
-            .push(
-                row![input, buttons]
+            contents = contents
+                .push(
+                    text(format!("Preview ({} lines)", rows.len())).size(20)
+                )
+                .push(
+                    scrollable(preview).height(250)
+                )
+                .push(
+                    row![
+                        text(problem.clone().unwrap_or_default()),
+                        horizontal_space(),
+                        button(text(format!("Import {} item(s)", rows.len()))).padding([12, 24])
+                            .on_press_maybe((problem.is_none() && !rows.is_empty()).then_some(Message::ImportConfirm)),
+                    ]
+                    .spacing(10)
+                    .align_y(Alignment::Center)
                 );
+        }
 
+        contents = contents.push(text(status.as_str()));
 
-        let content: Element<_> = column![contents]
+        let content: Element<_> = column![controls, contents]
             .into();
         content
     }
 
-    fn initialize_choice(&self) -> Element<Message> {
-        let Screen::InitializeChoice(input_value) = &self.screen else {
-            unreachable!("already checked for screen state");
+    fn trash(&self) -> Element<'_, Message> {
+        let Screen::Trash { items, days, confirming, status } = &self.screen else {
+            unreachable!("should have already checked for this state");
         };
+        let controls = self.get_controls();
 
-        let input = text_input("Enter absolute path for database", input_value)
-            .id("new-database")
-            .on_input(Message::InitializeInputChanged)
-            .on_submit(Message::InitializeSubmit);
-
-
-        let file_picker_button = padded_button("open file picker")
-            .on_press(Message::InitializeOpenFilePicker);
+        let mut list = column![].spacing(5);
+        for item in items.iter() {
+            list = list.push(
+                row![
+                    column![
+                        text(format!("{} x{}", item.item_name, item.quantity)),
+                        text(format!("From {}, deleted {} UTC", item.location_path, item.deleted_at.as_deref().unwrap_or_default())),
+                    ],
+                    horizontal_space(),
+                    padded_button("Details").on_press(Message::DetailPressed(item.item_id)),
+                    padded_button("Restore").on_press(Message::RestoreItem(item.item_id)),
+                    padded_button("Delete for good").on_press(Message::PurgeSelect(vec![item.item_id])),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center)
+            );
+        }
 
-        let submit_input_button = padded_button("Create Database")
-            .on_press(Message::InitializeSubmit);
+        let all_items = items.iter()
+            .map(|item| item.item_id)
+            .collect::<Vec<_>>();
+        let actions = if confirming.is_empty() {
+            row![
+                horizontal_space(),
+                padded_button("Empty trash")
+                    .on_press_maybe((!items.is_empty()).then_some(Message::PurgeSelect(all_items))),
+            ]
+        } else {
+            row![
+                text(format!("This will permanently delete {} item(s) with their photos, it can't be undone", confirming.len())),
+                horizontal_space(),
+                padded_button("Delete for good").on_press(Message::PurgeConfirm),
+                padded_button("Cancel").on_press(Message::PurgeCancel),
+            ]
+        };
 
-        let buttons = column![submit_input_button, file_picker_button];
-        
-        let contents = Self::container("Create a new database")
+        let contents = Self::container("Trash")
             .push(
-                "Create a new database to begin"
+                "Deleted items wait here until they are restored or deleted for good."
             )
             .push(
-                row![input, buttons]
-                );
-
-        let mut database_list = column![];
-            
-        for config in self.config.database_paths.iter() {
-            use std::path::Path;
-            let text = text(config.as_str());
-
-            let (button_text, on_press) = if Path::new(config).exists() {
-                ("Open Database", Message::OpenDatabase(config.clone()))
-            } else {
-                ("Create Database", Message::CreateDatabase(config.clone()))
-            };
-
-            let button = padded_button(button_text)
-                .on_press(on_press);
-
-            database_list = database_list.push(row![text, button]);
-        }
+                row![
+                    checkbox("Delete items for good once they have been in the trash for", self.config.trash_days.is_some())
+                        .on_toggle(Message::TrashAutoPurgeToggled),
+                    text_input("30", days.as_str())
+                        .on_input(Message::TrashDaysUpdate)
+                        .width(60),
+                    text("days"),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center)
+            )
+            .push(
+                text(format!("In the trash ({})", items.len())).size(20)
+            )
+            .push(
+                scrollable(list).height(350)
+            )
+            .push(
+                actions
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+            )
+            .push(
+                text(status.as_str())
+            );
 
-        let content: Element<_> = column![database_list, contents]
+        let content: Element<_> = column![controls, contents]
             .into();
         content
     }
 
-    fn initialize_error(&self) -> Element<Message> {
-        let Screen::InitializeError(msg) = &self.screen else {
-            panic!("calling initialize_error when not set to proper state");
+    fn history(&self) -> Element<'_, Message> {
+        let Screen::History { entries, status } = &self.screen else {
+            unreachable!("should have already checked for this state");
         };
-        let contents = Self::container("Initialization Error")
+        let controls = self.get_controls();
+
+        let contents = Self::container("History")
             .push(
-                msg.as_str()
+                "Every item added, edited, moved or deleted in this catalog, newest first."
+            )
+            .push(
+                text(status.as_str())
+            )
+            .push(
+                scrollable(Self::history_list(entries, true))
             );
 
-        let content: Element<_> = column![contents]
+        let content: Element<_> = column![controls, contents]
             .into();
         content
     }
 
-    fn welcome(&self) -> Element<Message> {
+    /// A timestamp stored by SQLite, or a note that it wasn't recorded
+    fn timestamp_text(timestamp: &Option<String>) -> String {
+        match timestamp {
+            Some(timestamp) => format!("{} UTC", timestamp),
+            None => String::from("not recorded"),
+        }
+    }
+
+    fn browse(&self) -> Element<'_, Message> {
+        let Screen::Browse { trail, children, items, thumbnails } = &self.screen else {
+            unreachable!("should have already checked for this state");
+        };
         let controls = self.get_controls();
-        let contents = Self::container("Welcome!")
-            .push(
-                "This is a simple cataloging software, driven by sqlite"
+
+        let mut breadcrumbs = row![
+            button("All locations").on_press(Message::BrowseTo(None)),
+        ]
+        .spacing(5)
+        .align_y(Alignment::Center);
+        for location in trail.iter() {
+            breadcrumbs = breadcrumbs
+                .push(text("/"))
+                .push(button(text(location.name.as_str())).on_press(Message::BrowseTo(Some(location.location_id))));
+        }
+
+        let mut contents = Self::container("Browse")
+            .push(breadcrumbs);
+
+        if let Some(location) = trail.last() && !location.description.is_empty() {
+            contents = contents.push(
+                text(format!("{}: {}", location.kind, location.description))
+            );
+        }
+
+        if !children.is_empty() {
+            let mut list = column![].spacing(5);
+            for child in children.iter() {
+                let label = format!("{}: {} ({} items)", child.location.kind, child.location.name, child.item_count);
+                let mut entry = column![
+                    button(text(label)).on_press(Message::BrowseTo(Some(child.location.location_id))),
+                ];
+                if !child.location.description.is_empty() {
+                    entry = entry.push(text(child.location.description.as_str()));
+                }
+                list = list.push(entry);
+            }
+            contents = contents
+                .push(
+                    text(format!("Locations ({})", children.len())).size(20)
+                )
+                .push(list);
+        }
+
+        let mut list = column![];
+        for item in items.iter() {
+            list = list.push(
+                row![]
+                    .spacing(10)
+                    .push_maybe(
+                        item.thumbnail.as_ref()
+                            .and_then(|thumbnail| thumbnails.get(&thumbnail.photo_id))
+                            .map(|handle| image(handle.clone()).width(96).height(96))
+                    )
+                    .push(
+                        column![
+                            text(format!("Name: {}", item.item_name)),
+                            text(item.item_notes.as_str()),
+                            text(item.tags.join(", ")),
+                            text(Self::field_text(item)),
+                            Self::quantity_control(item),
+                        ]
+                    )
+                    .push(horizontal_space())
+                    .push(padded_button("Details").on_press(Message::DetailPressed(item.item_id)))
+                    .push(padded_button("Edit").on_press(Message::EditPressed(item.item_id)))
             );
+        }
+        if trail.is_empty() {
+            // only items from before locations were required end up here
+            if !items.is_empty() {
+                contents = contents
+                    .push(
+                        text(format!("Items without a location ({})", items.len())).size(20)
+                    )
+                    .push(
+                        scrollable(list)
+                    );
+            }
+        } else {
+            contents = contents
+                .push(
+                    text(format!("Items here ({})", items.len())).size(20)
+                )
+                .push(
+                    scrollable(list)
+                );
+        }
 
         let content: Element<_> = column![controls, contents]
             .into();
         content
     }
 
-    fn add(&self) -> Element<Message> {
-        let Screen::Add {
-            rack_number,
-            rack_error,
-            shelf_number,
-            shelf_error,
-            basket_number,
-            basket_error,
-            item_name,
-            item_notes
-        } = &self.screen else {
+    fn move_items(&self) -> Element<'_, Message> {
+        let Screen::Move { items, location, status } = &self.screen else {
             unreachable!("should have already checked for this state");
         };
         let controls = self.get_controls();
-        let contents = Self::container("Add")
+
+        let mut moving = column![];
+        for item in items.iter() {
+            moving = moving.push(
+                text(format!("{} x{} (now in {})", item.item_name, item.quantity, item.location_path))
+            );
+        }
+
+        let contents = Self::container("Move")
             .push(
-                "This is a simple cataloging software, driven by sqlite"
+                text(format!("Moving {} item(s)", items.len())).size(20)
             )
             .push(
-                row![
-                    Self::pair_input_text("Enter rack number", rack_number.as_str(), rack_error, Message::AddRackUpdate),
-                    Self::pair_input_text("Enter shelf number", shelf_number.as_str(), shelf_error, Message::AddShelfUpdate)
-                ])
+                scrollable(moving).height(150)
+            )
             .push(
-                row![
-                    Self::pair_input_text("Enter basket number", basket_number.as_str(), basket_error, Message::AddBasketUpdate),
-                    Self::pair_input_text("Enter item name", item_name.as_str(), "", Message::AddItemUpdate)
-                ]
+                Self::location_form(location)
             )
             .push(
                 row![
-                    Self::pair_input_text("Enter item notes", item_notes.as_str(), basket_error, Message::AddNotesUpdate),
+                    text(status.as_str()),
                     horizontal_space(),
-                    padded_button("Insert").on_press(Message::AddItem)
+                    padded_button("Move here").on_press(Message::MoveConfirm),
                 ]
             );
         let content: Element<_> = column![controls, contents]
@@ -838,81 +3230,158 @@ impl Catalog {
         content
     }
 
-    fn search(&self) -> Element<Message> {
-        let controls = self.get_controls();
-        let Screen::Search { query, result } = &self.screen else {
-            unreachable!("already checked for search state but incorrect");
+    fn fields(&self) -> Element<'_, Message> {
+        let Screen::Fields { fields, name, kind, choices, error } = &self.screen else {
+            unreachable!("should have already checked for this state");
         };
-        let mut contents = Self::container("Search")
-            .push(
-                "This is a simple cataloging software, driven by sqlite"
-            )
-            .push(
+        let controls = self.get_controls();
+
+        let mut list = column![].spacing(5);
+        for field in fields.iter() {
+            let description = if field.kind == FieldKind::Choice {
+                format!("{} ({}: {})", field.name, field.kind, field.choices.join(", "))
+            } else {
+                format!("{} ({})", field.name, field.kind)
+            };
+            list = list.push(
                 row![
-                    Self::pair_input_text("Enter Item Name", query.as_str(), "", Message::SearchQueryUpdate),
-                    padded_button("Search").on_press(Message::SearchQuery),
+                    text(description),
+                    horizontal_space(),
+                    padded_button("Delete").on_press(Message::FieldDelete(field.field_id)),
                 ]
+                .align_y(Alignment::Center)
             );
+        }
 
-        let mut added_result = false;
-        let mut results = column![];
-        for item in result.iter() {
-            if !added_result {
-                contents = contents
-                    .push(
-                        text("Results").size(20)
-                    );
-                added_result = true;
-            }
-            results = results
-                .push(
-                    column![
-                        text(format!("Rack: {}", item.rack_number)),
-                        text(format!("Shelf: {}", item.shelf_number)),
-                        text(format!("Basket: {}", item.basket_number)),
-                        text(format!("Name: {}", item.item_name)),
-                        horizontal_space(),
-                    ]
-                );
-        };
-        if added_result {
-            // only show results if there were any
-            contents = contents.push(
-                scrollable(results));
+        let mut form = row![
+            Self::pair_input_text("Enter field name", name.as_str(), "", Message::FieldNameUpdate),
+            column![
+                text("Kind"),
+                pick_list(FieldKind::ALL, Some(*kind), Message::FieldKindChanged),
+            ],
+        ]
+        .spacing(10);
+        if *kind == FieldKind::Choice {
+            form = form.push(
+                Self::pair_input_text("Enter the choices, separated by commas", choices.as_str(), "", Message::FieldChoicesUpdate)
+            );
         }
-        
+        form = form.push(horizontal_space())
+            .push(padded_button("Add field").on_press(Message::FieldCreate));
+
+        let contents = Self::container("Fields")
+            .push(
+                "Fields are extra details that any item in this catalog can have, such as a grade for comics or a platform for games. Deleting a field removes it from every item."
+            )
+            .push(
+                text(format!("Fields ({})", fields.len())).size(20)
+            )
+            .push(
+                scrollable(list).height(250)
+            )
+            .push(
+                text("New field").size(20)
+            )
+            .push(form)
+            .push(
+                text(error.as_str())
+            );
+
         let content: Element<_> = column![controls, contents]
             .into();
         content
     }
 
-    fn delete(&self) -> Element<Message> {
+    fn delete(&self) -> Element<'_, Message> {
         let controls = self.get_controls();
-        let Screen::Delete { item_name, result } = &self.screen else {
+        let Screen::Delete { item_name, matches, confirming, result } = &self.screen else {
             unreachable!("already checked for delete state but incorrect");
         };
         let mut contents = Self::container("Delete")
             .push(
                 row![
                     Self::pair_input_text("Enter Item Name", item_name.as_str(), "", Message::DeleteQueryUpdate),
-                    padded_button("Delete").on_press(Message::SearchQuery),
+                    padded_button("Find").on_press(Message::DeleteQuery),
                 ]
             );
 
-        if result.len() > 0 {
-            contents = contents.push(
-                text("Item Successfully deleted:").size(20)
-            )
+        if let Some(item) = confirming {
+            contents = contents
+                .push(
+                    text(if item.quantity > 1 {
+                        format!("This will move all {} of these to the trash:", item.quantity)
+                    } else {
+                        String::from("This will move to the trash:")
+                    }).size(20)
+                )
+                .push(
+                    column![
+                        text(format!("Name: {}", item.item_name)),
+                        text(format!("Location: {}", item.location_path)),
+                        text(format!("Quantity: {}", item.quantity)),
+                        text(format!("Notes: {}", item.item_notes)),
+                    ]
+                )
+                .push(
+                    row![
+                        padded_button("Delete it").on_press(Message::DeleteConfirm),
+                        padded_button("Cancel").on_press(Message::DeleteCancel),
+                    ]
+                );
+        } else if !matches.is_empty() {
+            let mut list = column![];
+            for item in matches.iter() {
+                list = list.push(
+                    row![
+                        column![
+                            text(format!("Name: {} (x{})", item.item_name, item.quantity)),
+                            text(format!("Location: {}", item.location_path)),
+                        ],
+                        horizontal_space(),
+                        padded_button("Delete").on_press(Message::DeleteSelect(item.item_id)),
+                    ]
+                );
+            }
+            contents = contents
                 .push(
-                    text(item_name.as_str())
+                    text("Pick the item to delete").size(20)
+                )
+                .push(
+                    scrollable(list)
                 );
         }
 
+        if !result.is_empty() {
+            contents = contents.push(
+                text(result.as_str()).size(20)
+            );
+        }
+
         let content: Element<_> = column![controls, contents]
             .into();
         content
     }
 
+    /// Renders text marked up by a full text search with the matched terms in bold
+    fn highlighted_text<'a>(label: &'a str, marked: &'a str) -> Element<'a, Message> {
+        let bold = Font {
+            weight: font::Weight::Bold,
+            ..Font::default()
+        };
+
+        let mut spans = vec![span(label)];
+        for (index, part) in marked.split([catalog::HIGHLIGHT_START, catalog::HIGHLIGHT_END]).enumerate() {
+            // the markers alternate, so every odd part is a match
+            if index % 2 == 1 {
+                spans.push(span(part).font(bold).underline(true));
+            } else {
+                spans.push(span(part));
+            }
+        }
+
+        rich_text(spans).into()
+    }
+
     fn pair_input_text<'a>(label_text: &'a str, input: &'a str, error: &'a str, message: impl Fn(String) -> Message + 'a) -> Column<'a, Message> {
         column![
             text(label_text),
@@ -938,3 +3407,32 @@ impl Default for Catalog {
 fn padded_button<Message: Clone>(label: &str) -> Button<'_, Message> {
     button(text(label)).padding([12, 24])
 }
+
+/// The images shown for the thumbnails on a screen, by photo id
+type Thumbnails = HashMap<i64, image::Handle>;
+
+/// Makes the image for each of `thumbnails`, once, when they arrive.
+/// iced keys its image cache by handle, so a handle made afresh on every redraw would decode the PNG again each time.
+fn thumbnail_images<'a>(thumbnails: impl IntoIterator<Item = &'a Thumbnail>) -> Thumbnails {
+    thumbnails.into_iter()
+        .map(|thumbnail| (thumbnail.photo_id, image::Handle::from_bytes(thumbnail.png.clone())))
+        .collect()
+}
+
+/// Explains `err` along with what the user can do about it, e.g.
+/// "the catalog is in use by another program. Close any other program using the catalog, such as another Catalog window, then try again."
+fn explain(err: &CatalogError) -> String {
+    let advice = match err {
+        CatalogError::MissingFile(_) => "It may have been moved or deleted. Pick it again with the file picker, or press Create Database to start a new catalog there.",
+        CatalogError::NotACatalog(_) => "Pick a .sqlite file that Catalog created, or enter a new path and press Create Database.",
+        CatalogError::SchemaTooNew { .. } => "Install the latest version of Catalog to open it, the file has been left untouched.",
+        CatalogError::ConstraintViolation(_) => "Change what you entered and try again, nothing was saved.",
+        CatalogError::Locked => "Close any other program using the catalog, such as another Catalog window, then try again.",
+        CatalogError::Io(_) => "Check that the drive is still connected and has free space, and that you are allowed to read and write the file.",
+        CatalogError::Database(_) => "Nothing was saved. Try again, and if it keeps happening restore the catalog from a backup.",
+        CatalogError::NoSuchItem(_) => "It may have been deleted in the meantime.",
+        CatalogError::Invalid(_) => return err.to_string(),
+    };
+
+    format!("{}. {}", err, advice)
+}
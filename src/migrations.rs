@@ -0,0 +1,489 @@
+use sqlx::{SqliteConnection, Row};
+
+use crate::CatalogError;
+
+
+/// Forward migrations, applied in order.
+/// The schema version of a catalog is the number of migrations that have been applied to it
+/// and is stored in the database header with `PRAGMA user_version`.
+/// Never edit a migration once it has shipped, append a new one instead.
+const MIGRATIONS: &[&[&str]] = &[
+    // 1: Rack -> Shelf -> Basket layout
+    &[
+        "CREATE TABLE Rack (rack_id UNSIGNED BIG INT, PRIMARY KEY (rack_id))",
+        "CREATE TABLE Shelf (shelf_id UNSIGNED BIG INT, rack_id UNSIGNED BIG INT, PRIMARY KEY (shelf_id), FOREIGN KEY (rack_id) REFERENCES Rack(rack_id))",
+        "CREATE TABLE Basket (shelf_id UNSIGNED BIG INT, basket_id UNSIGNED BIG INT, PRIMARY KEY (basket_id), FOREIGN KEY (shelf_id) REFERENCES Shelf(shelf_id))",
+        "CREATE TABLE Item (item_id INTEGER PRIMARY KEY AUTOINCREMENT, notes TEXT, name TEXT, rack_id UNSIGNED BIG INT, shelf_id UNSIGNED BIG INT, basket_id UNSIGNED BIG INT, FOREIGN KEY (shelf_id) REFERENCES Shelf(shelf_id) ON DELETE CASCADE, FOREIGN KEY (basket_id) REFERENCES Basket(basket_id) ON DELETE CASCADE, FOREIGN KEY (rack_id) REFERENCES Rack(rack_id) ON DELETE CASCADE)",
+        "CREATE INDEX index_item_name ON Item (name)",
+    ],
+    // 2: shelves are scoped to racks and baskets to shelves.
+    // The locations are rebuilt from the items since version 1 never recorded the parents.
+    &[
+        "ALTER TABLE Item RENAME TO Item_v1",
+        "ALTER TABLE Basket RENAME TO Basket_v1",
+        "ALTER TABLE Shelf RENAME TO Shelf_v1",
+        "ALTER TABLE Rack RENAME TO Rack_v1",
+        "CREATE TABLE Rack (rack_id UNSIGNED BIG INT NOT NULL, PRIMARY KEY (rack_id))",
+        "CREATE TABLE Shelf (rack_id UNSIGNED BIG INT NOT NULL, shelf_id UNSIGNED BIG INT NOT NULL, PRIMARY KEY (rack_id, shelf_id), FOREIGN KEY (rack_id) REFERENCES Rack(rack_id) ON DELETE CASCADE)",
+        "CREATE TABLE Basket (rack_id UNSIGNED BIG INT NOT NULL, shelf_id UNSIGNED BIG INT NOT NULL, basket_id UNSIGNED BIG INT NOT NULL, PRIMARY KEY (rack_id, shelf_id, basket_id), FOREIGN KEY (rack_id, shelf_id) REFERENCES Shelf(rack_id, shelf_id) ON DELETE CASCADE)",
+        "CREATE TABLE Item (item_id INTEGER PRIMARY KEY AUTOINCREMENT, notes TEXT, name TEXT, rack_id UNSIGNED BIG INT, shelf_id UNSIGNED BIG INT, basket_id UNSIGNED BIG INT, FOREIGN KEY (rack_id, shelf_id, basket_id) REFERENCES Basket(rack_id, shelf_id, basket_id) ON DELETE CASCADE)",
+        "INSERT INTO Rack (rack_id) SELECT rack_id FROM Rack_v1 WHERE rack_id IS NOT NULL UNION SELECT rack_id FROM Item_v1 WHERE rack_id IS NOT NULL",
+        "INSERT INTO Shelf (rack_id, shelf_id) SELECT DISTINCT rack_id, shelf_id FROM Item_v1 WHERE rack_id IS NOT NULL AND shelf_id IS NOT NULL",
+        "INSERT INTO Basket (rack_id, shelf_id, basket_id) SELECT DISTINCT rack_id, shelf_id, basket_id FROM Item_v1 WHERE rack_id IS NOT NULL AND shelf_id IS NOT NULL AND basket_id IS NOT NULL",
+        "INSERT INTO Item (item_id, notes, name, rack_id, shelf_id, basket_id) SELECT item_id, notes, name, rack_id, shelf_id, basket_id FROM Item_v1",
+        "DROP TABLE Item_v1",
+        "DROP TABLE Basket_v1",
+        "DROP TABLE Shelf_v1",
+        "DROP TABLE Rack_v1",
+        "CREATE INDEX index_item_name ON Item (name)",
+        "CREATE INDEX index_item_location ON Item (rack_id, shelf_id, basket_id)",
+    ],
+    // 3: the fixed Rack -> Shelf -> Basket tables are replaced by a tree of named locations.
+    // Existing racks, shelves and baskets become "Rack 1", "Shelf 2", ... nodes of the matching kind.
+    &[
+        "CREATE TABLE Location (location_id INTEGER PRIMARY KEY AUTOINCREMENT, parent_id INTEGER, kind TEXT NOT NULL, name TEXT NOT NULL, FOREIGN KEY (parent_id) REFERENCES Location(location_id) ON DELETE CASCADE)",
+        "CREATE UNIQUE INDEX index_location_child ON Location (IFNULL(parent_id, 0), name)",
+        "INSERT INTO Location (parent_id, kind, name) SELECT NULL, 'Rack', 'Rack ' || rack_id FROM Rack",
+        "INSERT INTO Location (parent_id, kind, name) SELECT rack.location_id, 'Shelf', 'Shelf ' || Shelf.shelf_id FROM Shelf JOIN Location AS rack ON rack.parent_id IS NULL AND rack.name = 'Rack ' || Shelf.rack_id",
+        "INSERT INTO Location (parent_id, kind, name) SELECT shelf.location_id, 'Basket', 'Basket ' || Basket.basket_id FROM Basket JOIN Location AS rack ON rack.parent_id IS NULL AND rack.name = 'Rack ' || Basket.rack_id JOIN Location AS shelf ON shelf.parent_id = rack.location_id AND shelf.name = 'Shelf ' || Basket.shelf_id",
+        "ALTER TABLE Item RENAME TO Item_v2",
+        "CREATE TABLE Item (item_id INTEGER PRIMARY KEY AUTOINCREMENT, notes TEXT, name TEXT, location_id INTEGER, FOREIGN KEY (location_id) REFERENCES Location(location_id))",
+        "INSERT INTO Item (item_id, notes, name, location_id) SELECT item_id, notes, name, (SELECT basket.location_id FROM Location AS rack JOIN Location AS shelf ON shelf.parent_id = rack.location_id JOIN Location AS basket ON basket.parent_id = shelf.location_id WHERE rack.parent_id IS NULL AND rack.name = 'Rack ' || Item_v2.rack_id AND shelf.name = 'Shelf ' || Item_v2.shelf_id AND basket.name = 'Basket ' || Item_v2.basket_id) FROM Item_v2",
+        "DROP TABLE Item_v2",
+        "DROP TABLE Basket",
+        "DROP TABLE Shelf",
+        "DROP TABLE Rack",
+        "CREATE INDEX index_item_name ON Item (name)",
+        "CREATE INDEX index_item_location ON Item (location_id)",
+    ],
+    // 4: locations can carry a longer description alongside their name
+    &[
+        "ALTER TABLE Location ADD COLUMN description TEXT",
+    ],
+    // 5: full text index over item names and notes, kept in sync with Item by triggers
+    &[
+        "CREATE VIRTUAL TABLE ItemSearch USING fts5(name, notes, content='Item', content_rowid='item_id')",
+        "CREATE TRIGGER item_search_insert AFTER INSERT ON Item BEGIN INSERT INTO ItemSearch (rowid, name, notes) VALUES (new.item_id, new.name, new.notes); END",
+        "CREATE TRIGGER item_search_delete AFTER DELETE ON Item BEGIN INSERT INTO ItemSearch (ItemSearch, rowid, name, notes) VALUES ('delete', old.item_id, old.name, old.notes); END",
+        "CREATE TRIGGER item_search_update AFTER UPDATE OF name, notes ON Item BEGIN INSERT INTO ItemSearch (ItemSearch, rowid, name, notes) VALUES ('delete', old.item_id, old.name, old.notes); INSERT INTO ItemSearch (rowid, name, notes) VALUES (new.item_id, new.name, new.notes); END",
+        "INSERT INTO ItemSearch (ItemSearch) VALUES ('rebuild')",
+    ],
+    // 6: free form tags, any number per item
+    &[
+        "CREATE TABLE Tag (tag_id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE COLLATE NOCASE)",
+        "CREATE TABLE ItemTag (item_id INTEGER NOT NULL, tag_id INTEGER NOT NULL, PRIMARY KEY (item_id, tag_id), FOREIGN KEY (item_id) REFERENCES Item(item_id) ON DELETE CASCADE, FOREIGN KEY (tag_id) REFERENCES Tag(tag_id) ON DELETE CASCADE)",
+        "CREATE INDEX index_item_tag_tag ON ItemTag (tag_id)",
+    ],
+    // 7: a row can stand for several identical things
+    &[
+        "ALTER TABLE Item ADD COLUMN quantity INTEGER NOT NULL DEFAULT 1",
+    ],
+    // 8: fields declared by the user for their own catalog, e.g. a grade for comics or a platform for games.
+    // ItemField.value has no type so that numbers are stored and compared as numbers.
+    &[
+        "CREATE TABLE Field (field_id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE COLLATE NOCASE, kind TEXT NOT NULL CHECK (kind IN ('text', 'number', 'date', 'choice')), choices TEXT)",
+        "CREATE TABLE ItemField (item_id INTEGER NOT NULL, field_id INTEGER NOT NULL, value NOT NULL, PRIMARY KEY (item_id, field_id), FOREIGN KEY (item_id) REFERENCES Item(item_id) ON DELETE CASCADE, FOREIGN KEY (field_id) REFERENCES Field(field_id) ON DELETE CASCADE)",
+    ],
+    // 9: photos of items, kept inside of the catalog along with a small thumbnail of each
+    &[
+        "CREATE TABLE Photo (photo_id INTEGER PRIMARY KEY AUTOINCREMENT, item_id INTEGER NOT NULL, file_name TEXT NOT NULL, image BLOB NOT NULL, thumbnail BLOB NOT NULL, FOREIGN KEY (item_id) REFERENCES Item(item_id) ON DELETE CASCADE)",
+        "CREATE INDEX index_photo_item ON Photo (item_id)",
+    ],
+    // 10: when each item was added and last changed, in UTC. Items from before this version have neither.
+    // An insert that already carries a created_at, such as an item being restored, keeps it.
+    &[
+        "ALTER TABLE Item ADD COLUMN created_at TEXT",
+        "ALTER TABLE Item ADD COLUMN updated_at TEXT",
+        "CREATE TRIGGER item_created AFTER INSERT ON Item WHEN new.created_at IS NULL BEGIN UPDATE Item SET created_at = datetime('now'), updated_at = datetime('now') WHERE item_id = new.item_id; END",
+        "CREATE TRIGGER item_modified AFTER UPDATE OF location_id, name, notes, quantity ON Item BEGIN UPDATE Item SET updated_at = datetime('now') WHERE item_id = new.item_id; END",
+        "CREATE TRIGGER item_photo_added AFTER INSERT ON Photo BEGIN UPDATE Item SET updated_at = datetime('now') WHERE item_id = new.item_id; END",
+        "CREATE TRIGGER item_photo_removed AFTER DELETE ON Photo BEGIN UPDATE Item SET updated_at = datetime('now') WHERE item_id = old.item_id; END",
+    ],
+    // 11: a record of every change made to the items. It outlives the items it describes,
+    // so it has no foreign key, and the triggers keep it from being rewritten.
+    &[
+        "CREATE TABLE History (history_id INTEGER PRIMARY KEY AUTOINCREMENT, item_id INTEGER NOT NULL, item_name TEXT NOT NULL, action TEXT NOT NULL, details TEXT NOT NULL, actor TEXT NOT NULL, happened_at TEXT NOT NULL DEFAULT (datetime('now')))",
+        "CREATE INDEX index_history_item ON History (item_id)",
+        "CREATE TRIGGER history_no_update BEFORE UPDATE ON History BEGIN SELECT RAISE(ABORT, 'the history can only be added to'); END",
+        "CREATE TRIGGER history_no_delete BEFORE DELETE ON History BEGIN SELECT RAISE(ABORT, 'the history can only be added to'); END",
+    ],
+    // 12: deleted items go to the trash first and are only removed for good when it is emptied
+    &[
+        "ALTER TABLE Item ADD COLUMN deleted_at TEXT",
+        "CREATE INDEX index_item_deleted ON Item (deleted_at)",
+    ],
+    // 13: location names ignore case like tag and field names do, so "garage" is the same location as "Garage".
+    // Siblings that only differ in case already get their id added to their name to tell them apart.
+    &[
+        "DROP INDEX index_location_child",
+        "UPDATE Location SET name = name || ' (' || location_id || ')' WHERE EXISTS (SELECT 1 FROM Location AS other WHERE IFNULL(other.parent_id, 0) = IFNULL(Location.parent_id, 0) AND other.name = Location.name COLLATE NOCASE AND other.location_id < Location.location_id)",
+        "CREATE UNIQUE INDEX index_location_child ON Location (IFNULL(parent_id, 0), name COLLATE NOCASE)",
+    ],
+];
+
+/// Every table the migrations create, with the version that creates it and the version that drops it, if any.
+/// Keep this in sync with the migrations above.
+const TABLE_VERSIONS: &[(&str, i64, Option<i64>)] = &[
+    ("Rack", 1, Some(3)),
+    ("Shelf", 1, Some(3)),
+    ("Basket", 1, Some(3)),
+    ("Item", 1, None),
+    ("Location", 3, None),
+    ("ItemSearch", 5, None),
+    ("Tag", 6, None),
+    ("ItemTag", 6, None),
+    ("Field", 8, None),
+    ("ItemField", 8, None),
+    ("Photo", 9, None),
+    ("History", 11, None),
+];
+
+/// The tables a catalog at schema `version` contains.
+/// Catalogs from newer versions of the app are expected to still have every table this one knows of.
+fn tables_at(version: i64) -> impl Iterator<Item = &'static str> {
+    let version = version.min(SCHEMA_VERSION);
+    TABLE_VERSIONS.iter()
+        .filter(move |(_, created, dropped)| *created <= version && dropped.is_none_or(|dropped| dropped > version))
+        .map(|(table, _, _)| *table)
+}
+
+/// The schema version this build of the app creates and understands.
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+/// The tables and columns of the first migration, which catalogs from before versioning are made of
+const LEGACY_TABLES: &[(&str, &[&str])] = &[
+    ("Rack", &["rack_id"]),
+    ("Shelf", &["shelf_id", "rack_id"]),
+    ("Basket", &["shelf_id", "basket_id"]),
+    ("Item", &["item_id", "notes", "name", "rack_id", "shelf_id", "basket_id"]),
+];
+
+/// Reads the schema version stamped on the database.
+/// Catalogs created before versioning existed have a `user_version` of 0 but already contain
+/// the tables from the first migration, so they are reported as version 1.
+pub async fn schema_version(connection: &mut SqliteConnection) -> Result<i64, CatalogError> {
+    let version = sqlx::query("PRAGMA user_version")
+        .fetch_one(&mut *connection)
+        .await?
+        .get::<i64, _>(0);
+
+    if version != 0 {
+        return Ok(version);
+    }
+
+    if is_legacy_catalog(&mut *connection).await? {
+        Ok(1)
+    } else {
+        Ok(0)
+    }
+}
+
+/// Whether every table of the first migration is there with all of its columns.
+/// An `Item` table alone isn't enough, plenty of other programs have one.
+async fn is_legacy_catalog(connection: &mut SqliteConnection) -> Result<bool, CatalogError> {
+    for (table, columns) in LEGACY_TABLES {
+        let found = sqlx::query("SELECT name FROM pragma_table_info($1)")
+            .bind(table)
+            .fetch_all(&mut *connection)
+            .await?
+            .iter()
+            .map(|row| row.get::<String, _>("name"))
+            .collect::<Vec<_>>();
+
+        if !columns.iter().all(|column| found.iter().any(|name| name == column)) {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Brings the database up to `SCHEMA_VERSION` by applying every migration it is missing.
+/// This should be run inside of a transaction so that a failed migration leaves the file untouched.
+pub async fn migrate(connection: &mut SqliteConnection) -> Result<(), CatalogError> {
+    let version = schema_version(&mut *connection).await?;
+
+    if version > SCHEMA_VERSION {
+        return Err(CatalogError::SchemaTooNew { version, supported: SCHEMA_VERSION });
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        for stmt in migration.iter() {
+            sqlx::query(stmt)
+                .execute(&mut *connection)
+                .await
+                .map_err(|err| match CatalogError::from(err) {
+                    CatalogError::Database(err) => CatalogError::Database(format!("migration {} failed: {}", index + 1, err)),
+                    err => err,
+                })?;
+        }
+    }
+
+    // PRAGMA statements can't take bound parameters
+    sqlx::query(&format!("PRAGMA user_version = {}", SCHEMA_VERSION))
+        .execute(&mut *connection)
+        .await?;
+
+    Ok(())
+}
+
+async fn user_tables(connection: &mut SqliteConnection) -> Result<Vec<String>, CatalogError> {
+    let rows = sqlx::query("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'")
+        .fetch_all(&mut *connection)
+        .await?;
+
+    Ok(rows.into_iter()
+        .map(|row| row.get::<String, _>("name"))
+        .collect())
+}
+
+/// Gets a database ready for use as a catalog.
+/// A file with no tables is initialized from scratch, an existing catalog is migrated forward,
+/// and anything else is refused so that we never write our tables into somebody else's database.
+pub async fn prepare(connection: &mut SqliteConnection) -> Result<(), CatalogError> {
+    let tables = user_tables(&mut *connection).await?;
+
+    if tables.is_empty() {
+        return migrate(&mut *connection).await;
+    }
+
+    let version = schema_version(&mut *connection).await?;
+    if version == 0 {
+        return Err(CatalogError::NotACatalog(String::from("the file is an SQLite database, but it was not created by Catalog")));
+    }
+
+    // other programs stamp user_version too, so check the tables before writing anything or blaming the version
+    if let Some(missing) = tables_at(version).find(|table| !tables.iter().any(|name| name == table)) {
+        return Err(CatalogError::NotACatalog(format!(
+            "the file is an SQLite database marked as version {}, but it has no {} table, so it was not created by Catalog",
+            version,
+            missing
+        )));
+    }
+
+    migrate(&mut *connection).await?;
+
+    let tables = user_tables(&mut *connection).await?;
+    for table in tables_at(SCHEMA_VERSION) {
+        if !tables.iter().any(|name| name == table) {
+            return Err(CatalogError::NotACatalog(format!("the file is missing the {} table", table)));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{Connection, SqliteConnection, Row};
+
+    use super::{prepare, schema_version, tables_at, MIGRATIONS, SCHEMA_VERSION};
+    use crate::CatalogError;
+
+    async fn memory() -> SqliteConnection {
+        SqliteConnection::connect("sqlite::memory:").await.unwrap()
+    }
+
+    async fn execute(connection: &mut SqliteConnection, statements: &[&str]) {
+        for stmt in statements {
+            sqlx::query(stmt).execute(&mut *connection).await.unwrap();
+        }
+    }
+
+    /// Runs `prepare` the way the app does, in a transaction of its own
+    async fn prepare_in_transaction(connection: &mut SqliteConnection) -> Result<(), CatalogError> {
+        let mut transaction = connection.begin().await.unwrap();
+        prepare(&mut transaction).await?;
+        transaction.commit().await.unwrap();
+        Ok(())
+    }
+
+    /// Every item with its name, notes and full location path, by id
+    async fn items(connection: &mut SqliteConnection) -> Vec<(i64, String, String, Option<String>)> {
+        sqlx::query(
+            "WITH RECURSIVE LocationPath(location_id, path) AS (
+                SELECT location_id, name FROM Location WHERE parent_id IS NULL
+                UNION ALL
+                SELECT Location.location_id, LocationPath.path || ' / ' || Location.name FROM Location JOIN LocationPath ON Location.parent_id = LocationPath.location_id
+            )
+            SELECT Item.item_id, Item.name, Item.notes, LocationPath.path FROM Item LEFT JOIN LocationPath ON Item.location_id = LocationPath.location_id ORDER BY Item.item_id")
+            .fetch_all(&mut *connection)
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| (row.get("item_id"), row.get("name"), row.get("notes"), row.get("path")))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn empty_file_becomes_a_catalog() {
+        let mut connection = memory().await;
+
+        prepare_in_transaction(&mut connection).await.unwrap();
+
+        assert_eq!(schema_version(&mut connection).await.unwrap(), SCHEMA_VERSION);
+        for table in tables_at(SCHEMA_VERSION) {
+            let found = sqlx::query("SELECT name FROM sqlite_master WHERE name = $1")
+                .bind(table)
+                .fetch_optional(&mut connection)
+                .await
+                .unwrap();
+            assert!(found.is_some(), "{} is missing", table);
+        }
+    }
+
+    #[tokio::test]
+    async fn legacy_catalog_keeps_its_items_and_locations() {
+        let mut connection = memory().await;
+        // a catalog from before versioning, with user_version still 0
+        execute(&mut connection, MIGRATIONS[0]).await;
+        execute(&mut connection, &[
+            "INSERT INTO Rack (rack_id) VALUES (1), (2)",
+            "INSERT INTO Shelf (shelf_id, rack_id) VALUES (1, 1), (2, 1)",
+            "INSERT INTO Basket (shelf_id, basket_id) VALUES (1, 4)",
+            "INSERT INTO Item (item_id, notes, name, rack_id, shelf_id, basket_id) VALUES (3, 'blue shade', 'Lamp', 1, 1, 4)",
+            "INSERT INTO Item (item_id, notes, name, rack_id, shelf_id, basket_id) VALUES (7, '', 'Duck Tales 2', 2, 1, 4)",
+            "INSERT INTO Item (item_id, notes, name, rack_id, shelf_id, basket_id) VALUES (9, 'loose', 'Stapler', NULL, NULL, NULL)",
+        ]).await;
+        assert_eq!(schema_version(&mut connection).await.unwrap(), 1);
+
+        prepare_in_transaction(&mut connection).await.unwrap();
+
+        assert_eq!(schema_version(&mut connection).await.unwrap(), SCHEMA_VERSION);
+        assert_eq!(items(&mut connection).await, vec![
+            (3, String::from("Lamp"), String::from("blue shade"), Some(String::from("Rack 1 / Shelf 1 / Basket 4"))),
+            (7, String::from("Duck Tales 2"), String::from(""), Some(String::from("Rack 2 / Shelf 1 / Basket 4"))),
+            (9, String::from("Stapler"), String::from("loose"), None),
+        ]);
+
+        let kinds = sqlx::query("SELECT kind FROM Location ORDER BY location_id")
+            .fetch_all(&mut connection)
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get::<String, _>("kind"))
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, ["Rack", "Rack", "Shelf", "Shelf", "Basket", "Basket"]);
+
+        // later migrations fill in their defaults for the existing items
+        let row = sqlx::query("SELECT quantity, deleted_at FROM Item WHERE item_id = 3")
+            .fetch_one(&mut connection)
+            .await
+            .unwrap();
+        assert_eq!(row.get::<i64, _>("quantity"), 1);
+        assert_eq!(row.get::<Option<String>, _>("deleted_at"), None);
+
+        // and the full text index is built from the items that were already there
+        let found = sqlx::query("SELECT rowid FROM ItemSearch WHERE ItemSearch MATCH 'shade'")
+            .fetch_all(&mut connection)
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get::<i64, _>(0))
+            .collect::<Vec<_>>();
+        assert_eq!(found, [3]);
+    }
+
+    #[tokio::test]
+    async fn current_catalog_is_left_alone() {
+        let mut connection = memory().await;
+        prepare_in_transaction(&mut connection).await.unwrap();
+        execute(&mut connection, &[
+            "INSERT INTO Location (parent_id, kind, name) VALUES (NULL, 'Room', 'Garage')",
+            "INSERT INTO Item (notes, name, location_id) VALUES ('', 'Lamp', 1)",
+        ]).await;
+
+        prepare_in_transaction(&mut connection).await.unwrap();
+
+        assert_eq!(items(&mut connection).await, vec![
+            (1, String::from("Lamp"), String::from(""), Some(String::from("Garage"))),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn newer_catalog_is_refused() {
+        let mut connection = memory().await;
+        prepare_in_transaction(&mut connection).await.unwrap();
+        execute(&mut connection, &[&format!("PRAGMA user_version = {}", SCHEMA_VERSION + 1)]).await;
+
+        let result = prepare_in_transaction(&mut connection).await;
+
+        assert_eq!(result, Err(CatalogError::SchemaTooNew { version: SCHEMA_VERSION + 1, supported: SCHEMA_VERSION }));
+    }
+
+    #[tokio::test]
+    async fn foreign_database_is_refused() {
+        let mut connection = memory().await;
+        execute(&mut connection, &["CREATE TABLE Contact (name TEXT)"]).await;
+
+        let result = prepare_in_transaction(&mut connection).await;
+
+        assert!(matches!(result, Err(CatalogError::NotACatalog(_))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn foreign_database_with_an_item_table_is_refused() {
+        let mut connection = memory().await;
+        execute(&mut connection, &["CREATE TABLE Item (id INTEGER PRIMARY KEY, title TEXT)"]).await;
+
+        let result = prepare_in_transaction(&mut connection).await;
+
+        assert!(matches!(result, Err(CatalogError::NotACatalog(_))), "{:?}", result);
+        // nothing was written to it
+        let tables = sqlx::query("SELECT name FROM sqlite_master WHERE type = 'table'")
+            .fetch_all(&mut connection)
+            .await
+            .unwrap();
+        assert_eq!(tables.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn foreign_database_with_an_older_version_is_refused() {
+        let mut connection = memory().await;
+        execute(&mut connection, &["CREATE TABLE Contact (name TEXT)", "PRAGMA user_version = 3"]).await;
+
+        let result = prepare_in_transaction(&mut connection).await;
+
+        assert!(matches!(result, Err(CatalogError::NotACatalog(_))), "{:?}", result);
+        // nothing was written to it
+        let tables = sqlx::query("SELECT name FROM sqlite_master WHERE type = 'table'")
+            .fetch_all(&mut connection)
+            .await
+            .unwrap();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(schema_version(&mut connection).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn foreign_database_with_a_newer_version_is_refused() {
+        let mut connection = memory().await;
+        execute(&mut connection, &["CREATE TABLE Contact (name TEXT)", "PRAGMA user_version = 50"]).await;
+
+        let result = prepare_in_transaction(&mut connection).await;
+
+        assert!(matches!(result, Err(CatalogError::NotACatalog(_))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn locations_differing_in_case_are_told_apart() {
+        let mut connection = memory().await;
+        // a catalog from before location names ignored case
+        execute(&mut connection, MIGRATIONS[..12].concat().as_slice()).await;
+        execute(&mut connection, &[
+            "PRAGMA user_version = 12",
+            "INSERT INTO Location (location_id, parent_id, kind, name) VALUES (1, NULL, 'Room', 'Garage'), (2, NULL, 'Room', 'garage'), (3, 1, 'Shelf', 'Top'), (4, 2, 'Shelf', 'Top')",
+            "INSERT INTO Item (notes, name, location_id) VALUES ('', 'Lamp', 3), ('', 'Tent', 4)",
+        ]).await;
+
+        prepare_in_transaction(&mut connection).await.unwrap();
+
+        assert_eq!(items(&mut connection).await, vec![
+            (1, String::from("Lamp"), String::from(""), Some(String::from("Garage / Top"))),
+            (2, String::from("Tent"), String::from(""), Some(String::from("garage (2) / Top"))),
+        ]);
+        let duplicate = sqlx::query("INSERT INTO Location (parent_id, kind, name) VALUES (NULL, 'Room', 'GARAGE')")
+            .execute(&mut connection)
+            .await;
+        assert!(duplicate.is_err());
+    }
+}
@@ -0,0 +1,343 @@
+//! Runs operations on a `CatalogStore` for the GUI and turns what they return into a `Message`.
+//! Each operation gets a clone of the open store, so the GUI can start another one while it runs.
+
+use std::path::PathBuf;
+
+use catalog::{export, import::{self, ImportRow}, AddOutcome, CatalogError, CatalogStore, Change, FieldKind, ItemInfo, LocationLevel, NewItem, SearchFilter, SearchMode};
+
+use crate::Message;
+
+
+pub async fn create_database(path: String) -> Message {
+    match CatalogStore::create(&path).await {
+        Ok(store) => Message::CreateDatabaseSuccess(store),
+        Err(err) => Message::CreateDatabaseFailure(path, err),
+    }
+}
+
+pub async fn open_database(path: String) -> Message {
+    match CatalogStore::open(&path).await {
+        Ok(store) => Message::OpenDatabaseSuccess(store),
+        Err(err) => Message::OpenDatabaseFailure(path, err),
+    }
+}
+
+pub async fn close_database(store: CatalogStore) -> Message {
+    store.close().await;
+    Message::ClosedDatabase
+}
+
+/// Turns the result of an operation into `success`, or into a `DatabaseTransactionFailure` explaining what went wrong
+fn finish<T>(
+    result: Result<T, CatalogError>,
+    success: impl FnOnce(T) -> Message,
+) -> Message {
+    match result {
+        Ok(value) => success(value),
+        Err(err) => Message::DatabaseTransactionFailure(err),
+    }
+}
+
+/// Adds a new item with a quantity of 1, see `CatalogStore::add_item`
+pub async fn insert(
+    store: CatalogStore,
+    location: Vec<LocationLevel>,
+    name: String,
+    notes: String,
+    tags: Vec<String>,
+    fields: Vec<(i64, String)>,
+    allow_identical: bool,
+) -> Message {
+    let item = NewItem {
+        location,
+        name,
+        notes,
+        quantity: 1,
+        tags,
+        fields,
+    };
+
+    let result = store.add_item(&item, allow_identical).await;
+    finish(result, |outcome| match outcome {
+        AddOutcome::Added(item) => Message::ItemAdded(Change::Added(item)),
+        AddOutcome::IdenticalFound(item) => Message::IdenticalItemFound(item),
+    })
+}
+
+pub async fn import(
+    store: CatalogStore,
+    rows: Vec<ImportRow>,
+    file_name: String,
+) -> Message {
+    let result = store.import(&rows, &file_name).await;
+    finish(result, Message::ItemsImported)
+}
+
+/// Reads the CSV file at `path` for the Import screen
+pub async fn load_import(path: PathBuf) -> Message {
+    let file_name = path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    Message::ImportFileLoaded(file_name, import::read(&path).await)
+}
+
+/// Loads everything in the catalog to be written to `path`
+pub async fn export(
+    store: CatalogStore,
+    path: PathBuf,
+) -> Message {
+    let result = store.export().await;
+    finish(result, |export| Message::ExportLoaded(path, export))
+}
+
+/// Writes what `export` loaded to `path`
+pub async fn save_export(
+    path: PathBuf,
+    export: export::Export,
+) -> Message {
+    let file_name = path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let result = export::save(&path, &export).await
+        .map(|()| format!("Exported {} item(s) to {}", export.items.len(), file_name));
+
+    Message::CatalogExported(result)
+}
+
+pub async fn search(
+    store: CatalogStore,
+    query: String,
+    mode: SearchMode,
+    filter: SearchFilter,
+) -> Message {
+    if mode == SearchMode::FullText {
+        let result = store.full_text_search(&query, &filter).await;
+        return finish(result, |hits| {
+            if hits.is_empty() {
+                Message::DatabaseSearchFailure
+            } else {
+                Message::DatabaseTextSearchSuccess(hits)
+            }
+        });
+    }
+
+    let result = store.search(&query, mode, &filter).await;
+    finish(result, found)
+}
+
+pub async fn search_by_location(
+    store: CatalogStore,
+    location: Vec<String>,
+    name: String,
+    filter: SearchFilter,
+) -> Message {
+    let result = store.search_by_location(&location, &name, &filter).await;
+    finish(result, found)
+}
+
+/// Shows the items a search found, or that it found nothing
+fn found(items: Vec<ItemInfo>) -> Message {
+    if items.is_empty() {
+        Message::DatabaseSearchFailure
+    } else {
+        Message::DatabaseSearchSuccess(items)
+    }
+}
+
+pub async fn rebuild_search_index(store: CatalogStore) -> Message {
+    let result = store.rebuild_search_index().await;
+    finish(result, |()| Message::DatabaseTransactionSuccess)
+}
+
+pub async fn tag_names(store: CatalogStore) -> Message {
+    let result = store.tag_names().await;
+    finish(result, Message::TagsLoaded)
+}
+
+pub async fn fields(store: CatalogStore) -> Message {
+    let result = store.fields().await;
+    finish(result, Message::FieldsLoaded)
+}
+
+pub async fn create_field(
+    store: CatalogStore,
+    name: String,
+    kind: FieldKind,
+    choices: Vec<String>,
+) -> Message {
+    let result = store.create_field(&name, kind, &choices).await;
+    finish(result, |()| Message::FieldsChanged)
+}
+
+pub async fn delete_field(
+    store: CatalogStore,
+    field_id: i64,
+) -> Message {
+    let result = store.delete_field(field_id).await;
+    finish(result, |()| Message::FieldsChanged)
+}
+
+pub async fn location_choices(
+    store: CatalogStore,
+    path: Vec<String>,
+) -> Message {
+    let result = store.location_choices(&path).await;
+    finish(result, Message::LocationChoicesLoaded)
+}
+
+/// Loads an item for the Detail screen
+pub async fn load_detail(
+    store: CatalogStore,
+    item_id: i64,
+) -> Message {
+    let result = store.load_item(item_id).await;
+    finish(result, |details| Message::DetailLoaded(details.item, details.location, details.photos))
+}
+
+/// Loads an item to open the Edit screen with
+pub async fn load_edit(
+    store: CatalogStore,
+    item_id: i64,
+) -> Message {
+    let result = store.load_item(item_id).await;
+    finish(result, |details| Message::EditLoaded(details.item, details.location, details.photos))
+}
+
+pub async fn browse(
+    store: CatalogStore,
+    location_id: Option<i64>,
+) -> Message {
+    let result = store.browse(location_id).await;
+    finish(result, |contents| Message::BrowseLoaded(contents.trail, contents.children, contents.items))
+}
+
+pub async fn update_item(
+    store: CatalogStore,
+    item_id: i64,
+    location: Vec<LocationLevel>,
+    name: String,
+    notes: String,
+    tags: Vec<String>,
+    fields: Vec<(i64, String)>,
+) -> Message {
+    let result = store.update_item(item_id, &location, &name, &notes, &tags, &fields).await;
+    finish(result, |before| Message::ItemUpdated(Change::Edited(before)))
+}
+
+pub async fn move_items(
+    store: CatalogStore,
+    item_ids: Vec<i64>,
+    location: Vec<LocationLevel>,
+) -> Message {
+    let result = store.move_items(&item_ids, &location).await;
+    finish(result, |moved| Message::ItemsMoved(Change::Moved(moved)))
+}
+
+pub async fn photos(
+    store: CatalogStore,
+    item_id: i64,
+) -> Message {
+    let result = store.photos(item_id).await;
+    finish(result, |photos| Message::PhotosLoaded(item_id, photos))
+}
+
+pub async fn attach_photos(
+    store: CatalogStore,
+    item_id: i64,
+    paths: Vec<PathBuf>,
+) -> Message {
+    let result = store.attach_photos(item_id, &paths).await;
+    finish(result, |()| Message::PhotosChanged(item_id))
+}
+
+pub async fn delete_photo(
+    store: CatalogStore,
+    item_id: i64,
+    photo_id: i64,
+) -> Message {
+    let result = store.delete_photo(item_id, photo_id).await;
+    finish(result, |()| Message::PhotosChanged(item_id))
+}
+
+pub async fn change_quantity(
+    store: CatalogStore,
+    item_id: i64,
+    by: i64,
+) -> Message {
+    let result = store.change_quantity(item_id, by).await;
+    finish(result, |item| {
+        let quantity = item.quantity;
+        let change = Change::QuantityChanged { item_id, item_name: item.item_name, by };
+        Message::QuantityChanged(item_id, quantity, change)
+    })
+}
+
+pub async fn delete(
+    store: CatalogStore,
+    item_id: i64,
+) -> Message {
+    let result = store.delete_item(item_id).await;
+    finish(result, |deleted| Message::ItemDeleted(deleted.map(Change::Deleted)))
+}
+
+pub async fn restore(
+    store: CatalogStore,
+    item_id: i64,
+) -> Message {
+    let result = store.restore_item(item_id).await;
+    finish(result, |restored| Message::ItemRestored(restored.map(Change::Restored)))
+}
+
+pub async fn purge(
+    store: CatalogStore,
+    item_ids: Vec<i64>,
+) -> Message {
+    let result = store.purge(&item_ids).await;
+    finish(result, Message::TrashPurged)
+}
+
+pub async fn purge_expired(
+    store: CatalogStore,
+    days: u32,
+) -> Message {
+    let result = store.purge_expired(days).await;
+    finish(result, |()| Message::DatabaseTransactionSuccess)
+}
+
+pub async fn trash(
+    store: CatalogStore,
+    purge_after: Option<u32>,
+) -> Message {
+    let result = store.trash(purge_after).await;
+    finish(result, Message::TrashLoaded)
+}
+
+pub async fn undo(
+    store: CatalogStore,
+    change: Change,
+) -> Message {
+    match store.undo(change.clone()).await {
+        Err(err) => Message::UndoFailed(change, err),
+        Ok(redo) => Message::Undone(redo),
+    }
+}
+
+pub async fn redo(
+    store: CatalogStore,
+    change: Change,
+) -> Message {
+    match store.redo(change.clone()).await {
+        Err(err) => Message::RedoFailed(change, err),
+        Ok(undo) => Message::Redone(undo),
+    }
+}
+
+pub async fn history(
+    store: CatalogStore,
+    item_id: Option<i64>,
+) -> Message {
+    let result = store.history(item_id).await;
+    finish(result, |entries| Message::HistoryLoaded(item_id, entries))
+}
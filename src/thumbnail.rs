@@ -0,0 +1,22 @@
+//! Small previews of the photos attached to items.
+
+use image::ImageOutputFormat;
+use std::io::Cursor;
+
+
+/// The longest side of a thumbnail in pixels
+pub const SIZE: u32 = 160;
+
+/// Decodes a photo and shrinks it to fit within `SIZE` by `SIZE`, keeping its aspect ratio,
+/// returning the thumbnail encoded as a PNG.
+pub fn make(photo: &[u8]) -> Result<Vec<u8>, String> {
+    let photo = image::load_from_memory(photo)
+        .map_err(|err| err.to_string())?;
+
+    let mut thumbnail = Cursor::new(Vec::new());
+    photo.thumbnail(SIZE, SIZE)
+        .write_to(&mut thumbnail, ImageOutputFormat::Png)
+        .map_err(|err| err.to_string())?;
+
+    Ok(thumbnail.into_inner())
+}